@@ -0,0 +1,41 @@
+use std::{sync::Barrier, thread, time::Instant};
+
+use multichannel::{DynMultiReceiver, Policy};
+
+// Measures how fast several consumers drain a large priority group in which every channel holds a single
+// message. The consumers race for the same messages, so many picks find their channel already emptied
+// by another consumer and have to move on to the next candidate.
+fn main() {
+    const CHANNELS: usize = 5_000;
+    const CONSUMERS: usize = 8;
+
+    for policy in [
+        Policy::RoundRobin,
+        Policy::Weighted,
+        Policy::DeterministicWeighted,
+    ] {
+        let mrx = DynMultiReceiver::<u64, u8>::new().with_policy(policy);
+        let senders: Vec<_> = (0..CHANNELS)
+            .map(|_| mrx.new_channel(0, 1, false, None))
+            .collect();
+        for (i, sender) in senders.iter().enumerate() {
+            sender.send(i as u64).unwrap();
+        }
+        let barrier = Barrier::new(CONSUMERS);
+        let start = Instant::now();
+        thread::scope(|scope| {
+            for _ in 0..CONSUMERS {
+                let (consumer, barrier) = (mrx.clone(), &barrier);
+                scope.spawn(move || {
+                    barrier.wait();
+                    while consumer.try_receive().is_ok() {}
+                });
+            }
+        });
+        let elapsed = start.elapsed();
+        println!(
+            "{:?}: {} consumers drained {} channels in {:?}",
+            policy, CONSUMERS, CHANNELS, elapsed
+        );
+    }
+}
@@ -0,0 +1,80 @@
+use std::{
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        Arc,
+    },
+    thread,
+    time::Instant,
+};
+
+use multichannel::DynMultiReceiver;
+
+// Measures the send/receive throughput of one busy priority group, first on its own, then while
+// another thread keeps toggling the frozen state of idle channels and a third one keeps creating
+// and removing channels. Freeze toggles only take the shared lock, so they barely slow down receive().
+fn main() {
+    const CHANNELS: usize = 1_000;
+    const MESSAGES: usize = 1_000_000;
+
+    for (toggle, churn) in [(false, false), (true, false), (false, true), (true, true)] {
+        let mrx = Arc::new(DynMultiReceiver::<u64, u8>::new());
+        let busy: Vec<_> = (0..16)
+            .map(|_| mrx.new_channel(0, 1, false, None))
+            .collect();
+        let idle: Vec<_> = (0..CHANNELS)
+            .map(|_| mrx.new_channel(1, 1, false, None))
+            .collect();
+        let done = Arc::new(AtomicBool::new(false));
+        let toggles = Arc::new(AtomicU64::new(0));
+        let registrations = Arc::new(AtomicU64::new(0));
+
+        let toggler = toggle.then(|| {
+            let (done, toggles) = (done.clone(), toggles.clone());
+            thread::spawn(move || {
+                let mut frozen = false;
+                while !done.load(Ordering::Relaxed) {
+                    frozen = !frozen;
+                    for sender in &idle {
+                        sender.set_frozen(frozen);
+                    }
+                    toggles.fetch_add(idle.len() as u64, Ordering::Relaxed);
+                }
+            })
+        });
+        let registrar = churn.then(|| {
+            let (mrx, done, registrations) = (mrx.clone(), done.clone(), registrations.clone());
+            thread::spawn(move || {
+                while !done.load(Ordering::Relaxed) {
+                    let sender = mrx.new_channel(2, 1, false, None);
+                    mrx.remove_channel(&sender);
+                    registrations.fetch_add(1, Ordering::Relaxed);
+                }
+            })
+        });
+
+        let start = Instant::now();
+        let producer = thread::spawn(move || {
+            for i in 0..MESSAGES {
+                busy[i % busy.len()].send(i as u64).unwrap();
+            }
+        });
+        for _ in 0..MESSAGES {
+            mrx.receive();
+        }
+        let elapsed = start.elapsed();
+        done.store(true, Ordering::Relaxed);
+        producer.join().unwrap();
+        for thread in toggler.into_iter().chain(registrar) {
+            thread.join().unwrap();
+        }
+
+        println!(
+            "toggling: {:5}, churn: {:5} | {:.0} msg/s, {:.0} toggles/s, {:.0} registrations/s",
+            toggle,
+            churn,
+            MESSAGES as f64 / elapsed.as_secs_f64(),
+            toggles.load(Ordering::Relaxed) as f64 / elapsed.as_secs_f64(),
+            registrations.load(Ordering::Relaxed) as f64 / elapsed.as_secs_f64(),
+        );
+    }
+}
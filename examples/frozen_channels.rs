@@ -0,0 +1,50 @@
+use std::time::Instant;
+
+use multichannel::DynMultiReceiver;
+
+// Measures the receive latency with 100k channels spread over 1000 priority tiers, of which only a few
+// have messages at any time: without any frozen channel, with a single frozen channel that keeps
+// a message buffered in the highest priority tier, and on a multichannel created without freezing.
+fn main() {
+    run("no frozen channel", DynMultiReceiver::new(), false);
+    run("one frozen channel", DynMultiReceiver::new(), true);
+    run(
+        "without freezing",
+        DynMultiReceiver::new().without_freezing(),
+        false,
+    );
+}
+
+fn run(name: &str, mrx: DynMultiReceiver<u64, u32>, freeze_one: bool) {
+    const TIERS: usize = 1_000;
+    const CHANNELS: usize = 100_000;
+    const ACTIVE: usize = 16;
+    const ROUNDS: usize = 20_000;
+
+    let senders: Vec<_> = (0..CHANNELS)
+        .map(|i| mrx.new_channel((i * TIERS / CHANNELS) as u32, 1, false, None))
+        .collect();
+    if freeze_one {
+        senders[0].send(u64::MAX).unwrap();
+        senders[0].set_frozen(true);
+    }
+
+    let start = Instant::now();
+    for round in 0..ROUNDS {
+        for i in 0..ACTIVE {
+            let channel = 1 + (round * 7919 + i * 613) % (CHANNELS - 1);
+            senders[channel].send(round as u64).unwrap();
+        }
+        for _ in 0..ACTIVE {
+            mrx.receive();
+        }
+    }
+    let elapsed = start.elapsed();
+    let messages = ROUNDS * ACTIVE;
+    println!(
+        "{}: {:.0} ns per receive ({:.0} msg/s)",
+        name,
+        elapsed.as_nanos() as f64 / messages as f64,
+        messages as f64 / elapsed.as_secs_f64()
+    );
+}
@@ -29,7 +29,7 @@ fn main() {
     // Send some messages
     int_sender.send(Msg::IntegerData(33)).unwrap();
     int_sender.send(Msg::IntegerData(4031)).unwrap();
-    float_sender.send(Msg::FloatingData(3.14)).unwrap();
+    float_sender.send(Msg::FloatingData(1.5)).unwrap();
     int_sender.send(Msg::IntegerData(2)).unwrap();
     float_sender.send(Msg::FloatingData(10.0)).unwrap();
     float_sender.send(Msg::FloatingData(0.0)).unwrap();
@@ -0,0 +1,59 @@
+use std::{sync::Barrier, thread, time::Instant};
+
+use multichannel::DynMultiReceiver;
+
+// Measures the receive throughput of many consumers sharing one multichannel, fed by a few producers.
+// Run it once as is and once with `--features parking-lot` to compare the std and the parking_lot locks.
+fn main() {
+    const PRODUCERS: usize = 4;
+    const CHANNELS_PER_PRODUCER: usize = 64;
+    const MESSAGES_PER_PRODUCER: usize = 250_000;
+
+    let backend = match cfg!(feature = "parking-lot") {
+        true => "parking_lot",
+        false => "std",
+    };
+    for consumers in [1, 4, 16, 64] {
+        let mrx = DynMultiReceiver::<u64, u8>::new();
+        let senders: Vec<Vec<_>> = (0..PRODUCERS)
+            .map(|producer| {
+                (0..CHANNELS_PER_PRODUCER)
+                    .map(|_| mrx.new_channel((producer % 2) as u8, 1, false, Some(64)))
+                    .collect()
+            })
+            .collect();
+        let messages = PRODUCERS * MESSAGES_PER_PRODUCER;
+        let per_consumer = messages / consumers;
+        let barrier = Barrier::new(PRODUCERS + consumers);
+        let start = Instant::now();
+        thread::scope(|scope| {
+            for channels in &senders {
+                let barrier = &barrier;
+                scope.spawn(move || {
+                    barrier.wait();
+                    for i in 0..MESSAGES_PER_PRODUCER {
+                        channels[i % channels.len()].send(i as u64).unwrap();
+                    }
+                });
+            }
+            for consumer in 0..consumers {
+                let (mrx, barrier) = (mrx.clone(), &barrier);
+                // The first consumer also takes what doesn't divide evenly
+                let amount = per_consumer + (consumer == 0) as usize * (messages % consumers);
+                scope.spawn(move || {
+                    barrier.wait();
+                    for _ in 0..amount {
+                        mrx.receive();
+                    }
+                });
+            }
+        });
+        let elapsed = start.elapsed();
+        println!(
+            "{}: {:2} consumers | {:.0} msg/s",
+            backend,
+            consumers,
+            messages as f64 / elapsed.as_secs_f64()
+        );
+    }
+}
@@ -0,0 +1,36 @@
+use std::time::Instant;
+
+use multichannel::DynMultiReceiver;
+
+// Measures the receive throughput of a single priority group with many identically configured channels,
+// of which only a few have messages at any time.
+fn main() {
+    const CHANNELS: usize = 10_000;
+    const ACTIVE: usize = 16;
+    const ROUNDS: usize = 10_000;
+
+    let mrx = DynMultiReceiver::<u64, u8>::new();
+    let senders: Vec<_> = (0..CHANNELS)
+        .map(|_| mrx.new_channel(0, 1, false, None))
+        .collect();
+
+    let start = Instant::now();
+    for round in 0..ROUNDS {
+        for i in 0..ACTIVE {
+            let sender = &senders[(round * 7919 + i * 613) % CHANNELS];
+            sender.send(round as u64).unwrap();
+        }
+        for _ in 0..ACTIVE {
+            mrx.receive();
+        }
+    }
+    let elapsed = start.elapsed();
+    let messages = ROUNDS * ACTIVE;
+    println!(
+        "{} messages through {} channels in {:?} ({:.0} msg/s)",
+        messages,
+        CHANNELS,
+        elapsed,
+        messages as f64 / elapsed.as_secs_f64()
+    );
+}
@@ -0,0 +1,48 @@
+use std::time::Instant;
+
+use multichannel::DynMultiReceiver;
+
+// Measures the receive throughput with many priority tiers, of which only a few low priority ones
+// have messages at any time.
+fn main() {
+    run("default", DynMultiReceiver::<u64, u32>::new());
+    run(
+        "without freezing",
+        DynMultiReceiver::<u64, u32>::new().without_freezing(),
+    );
+}
+
+fn run(name: &str, mrx: DynMultiReceiver<u64, u32>) {
+    const TIERS: usize = 1_000;
+    const CHANNELS_PER_TIER: usize = 100;
+    const ACTIVE: usize = 16;
+    const ROUNDS: usize = 2_000;
+
+    let senders: Vec<_> = (0..TIERS * CHANNELS_PER_TIER)
+        .map(|i| mrx.new_channel((i / CHANNELS_PER_TIER) as u32, 1, false, None))
+        .collect();
+
+    let start = Instant::now();
+    for round in 0..ROUNDS {
+        for i in 0..ACTIVE {
+            // Only the lower priority half of the tiers gets messages
+            let channel = (round * 7919 + i * 613) % (senders.len() / 2);
+            senders[senders.len() / 2 + channel]
+                .send(round as u64)
+                .unwrap();
+        }
+        for _ in 0..ACTIVE {
+            mrx.receive();
+        }
+    }
+    let elapsed = start.elapsed();
+    let messages = ROUNDS * ACTIVE;
+    println!(
+        "{}: {} messages through {} tiers in {:?} ({:.0} msg/s)",
+        name,
+        messages,
+        TIERS,
+        elapsed,
+        messages as f64 / elapsed.as_secs_f64()
+    );
+}
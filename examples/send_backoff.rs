@@ -0,0 +1,32 @@
+use std::time::Instant;
+
+use multichannel::{DynMultiReceiver, SendBackoff};
+
+// Measures how long a producer takes to push messages through a small bounded channel that is drained
+// as fast as possible, when a full channel parks the sender right away or spins before parking.
+fn main() {
+    const MESSAGES: u64 = 1_000_000;
+
+    for backoff in [SendBackoff::Park, SendBackoff::SpinThenPark { spins: 64 }] {
+        let mrx = DynMultiReceiver::<u64, u8>::new().with_send_backoff(backoff);
+        let sender = mrx.new_channel(0, 1, false, Some(4));
+        let start = Instant::now();
+        let producer = std::thread::spawn(move || {
+            for i in 0..MESSAGES {
+                sender.send(i).unwrap();
+            }
+        });
+        for _ in 0..MESSAGES {
+            mrx.receive();
+        }
+        producer.join().unwrap();
+        let elapsed = start.elapsed();
+        println!(
+            "{:?}: {} messages in {:?} ({:.0} msg/s)",
+            backoff,
+            MESSAGES,
+            elapsed,
+            MESSAGES as f64 / elapsed.as_secs_f64()
+        );
+    }
+}
@@ -0,0 +1,37 @@
+use std::time::Instant;
+
+use multichannel::DynMultiReceiver;
+
+// Measures the cost of creating channels that each open a new priority tier, with the tiers created
+// in ascending, descending and shuffled priority order. Descending is the worst case for the sorted
+// groups, as every new tier is inserted in front of all others and moves every later group.
+fn main() {
+    for tiers in [1_000u32, 3_000, 10_000] {
+        let ascending: Vec<_> = (0..tiers).collect();
+        let descending: Vec<_> = (0..tiers).rev().collect();
+        // A fixed permutation, so the runs are comparable
+        let shuffled: Vec<_> = (0..tiers)
+            .map(|i| (i as u64 * 7919 % tiers as u64) as u32)
+            .collect();
+        for (order, priorities) in [
+            ("ascending", ascending),
+            ("descending", descending),
+            ("shuffled", shuffled),
+        ] {
+            let mrx = DynMultiReceiver::<u64, u32>::new();
+            let start = Instant::now();
+            let senders: Vec<_> = priorities
+                .iter()
+                .map(|&priority| mrx.new_channel(priority, 1, false, None))
+                .collect();
+            let elapsed = start.elapsed();
+            println!(
+                "{:7} tiers, {:10} | {:?} total, {:.0} ns per tier",
+                tiers,
+                order,
+                elapsed,
+                elapsed.as_nanos() as f64 / senders.len() as f64
+            );
+        }
+    }
+}
@@ -11,18 +11,33 @@
 //! - No unsafe code
 //! - Multi producer and multi consumer
 //! 
+//! ## Priorities
+//! A smaller priority value means a higher priority, following the Ord of the priority type: receive() serves
+//! the channels with the smallest priority first. With `#[derive(Ord)]` on an enum, the first variant is the most urgent one,
+//! like `Priority::High` in the example below. For numbers, 0 is the most urgent priority.
+//!
 //! ## Performance
 //! The amount of functionality the DynMultiReceiver provides comes at a cost. Due to the freezing feature,
-//! every receive() call has a worst case of O(n) complexity, where n is the amount of channels. This is because
-//! the DynMultiReceiver has to iterate over all channels to find the highest priority channel with a message THAT IS NOT FROZEN.
-//! Otherwise using a heap would be a good idea, but the freezing feature makes this impossible.
-//! 
-//! So if you have a huge amount of channels and you are not using the freezing feature, you might want to consider using a different
+//! receive() can't use a heap to find the highest priority channel with a message THAT IS NOT FROZEN. Instead, every channel
+//! has a bit in a ready bitmap, which is set when it receives a message, and receive() only looks at channels with their bit set.
+//! So its cost grows with the amount of channels holding messages, plus a cheap pass over the bitmap (n / 64 words for n channels).
+//! A second bitmap marks the priority tiers with a ready channel, so tiers without one are skipped 64 at a time.
+//! If you never freeze channels, DynMultiReceiver::without_freezing() guarantees that a set bit always means a deliverable message.
+//! The frozen flags are atomics, so freezing and unfreezing only take the shared lock and run alongside receive().
+//! Creating and removing channels takes the exclusive lock, which waits for running receive() scans
+//! (see examples/freeze_toggling.rs). The tiers are kept sorted by priority and their bits follow that order,
+//! so creating or removing a tier shifts the lower priority tiers and their bits by one. Like inserting into
+//! a sorted Vec, that is linear in the amount of tiers, but only a memory move per tier, which only matters
+//! with many thousands of distinct priorities (see examples/tier_creation.rs).
+//! The parking-lot feature replaces the std locks with the parking_lot ones, which hold up better with many
+//! concurrent consumers (see examples/lock_backends.rs).
+//!
+//! So if you have a huge amount of channels with messages in them at the same time, you might want to consider using a different
 //! implementation. For most use cases, the performance should be good enough.
-//! 
+//!
 //! If you can implement your logic using only basic channels, you should do that. This implementation is meant for cases where
 //! you need more advanced features.
-//! 
+//!
 //! ## Hello World
 //! ```
 //!  use multichannel::DynMultiReceiver;
@@ -79,76 +94,290 @@
 
 
 
-use std::sync::{
-    atomic::{AtomicBool, AtomicUsize, Ordering},
-    Arc, Condvar, Mutex, RwLock,
+use std::{
+    collections::BTreeMap,
+    hash::BuildHasher,
+    sync::{
+        atomic::{AtomicBool, AtomicI64, AtomicIsize, AtomicU32, AtomicU64, AtomicUsize, Ordering},
+        Arc, OnceLock,
+    },
+    time::{Duration, Instant},
 };
 
-use ahash::{HashMap, HashSet};
-use rand::distributions::{Distribution, WeightedIndex};
+use ahash::HashMap;
+use crossbeam_utils::Backoff;
+use itertools::Either;
+use rand::{
+    rngs::{SmallRng, ThreadRng},
+    Rng, RngCore, SeedableRng,
+};
 use smallvec::SmallVec;
 use thiserror::Error;
 
+use sync::{recover, Condvar, Mutex, MutexGuard, RwLock};
+
+mod sync;
 mod tests;
 
 pub trait Priority: Ord {}
 impl<P: Ord> Priority for P {}
 
-struct DynState<T, P: Priority> {
+struct DynState<T, P: Priority, S: BuildHasher> {
     next_id: u32,
-    lookup: HashMap<u32, (usize, usize)>, // (group_idx, inner_idx)
+    lookup: std::collections::HashMap<u32, (usize, usize), S>, // (group key, inner_idx)
     groups: Vec<PriorityGroup<T, P>>,
+    // Stable keys of the groups, so inserting or removing a group doesn't have to adjust lookup
+    positions: Vec<usize>, // group key -> group_idx
+    free_keys: Vec<usize>,
+    pinned: Option<P>,
+    // Priority links, from the linked channel to its prerequisites
+    links: HashMap<u32, SmallVec<[u32; 2]>>,
+    // Set with new_named_channel()
+    names: HashMap<String, u32>,
+    // Handed out by events(), dropped once they return false
+    subscribers: Vec<EventSubscriber<P>>,
+    tier_defaults: Option<TierDefaultsFn<P>>,
+    // Set with set_group_policy(), kept while a tier has no channels
+    group_policies: BTreeMap<P, Policy>,
+    // False if freezing was disabled with without_freezing()
+    freezing: bool,
+    // Has the bit of a group set while it has a ready channel, so receive() skips the other groups at once
+    ready_tiers: Arc<TierMap>,
+    // Set with set_aging(), 0 if disabled
+    aging: u32,
+    // Messages selected since a lower group was last preferred by aging
+    aging_streak: AtomicU32,
 }
 
-impl<T, P: Priority> DynState<T, P> {
-    fn new() -> Self {
+type TierDefaultsFn<P> = Arc<dyn Fn(&P) -> (u32, bool) + Send + Sync>;
+// Returns false once the subscriber is gone
+type EventSubscriber<P> = Box<dyn Fn(ChannelEvent<&P>) -> bool + Send + Sync>;
+
+impl<T, P: Priority, S: BuildHasher> DynState<T, P, S> {
+    fn new(hasher: S) -> Self {
         Self {
             next_id: 0,
-            lookup: HashMap::default(),
+            lookup: std::collections::HashMap::with_hasher(hasher),
             groups: Vec::new(),
+            positions: Vec::new(),
+            free_keys: Vec::new(),
+            pinned: None,
+            links: HashMap::default(),
+            names: HashMap::default(),
+            subscribers: Vec::new(),
+            tier_defaults: None,
+            group_policies: BTreeMap::new(),
+            freezing: true,
+            ready_tiers: Arc::new(TierMap::new()),
+            aging: 0,
+            aging_streak: AtomicU32::new(0),
+        }
+    }
+
+    /// Returns an unused channel id. Ids wrap around after u32::MAX, skipping the ids of live channels.
+    fn alloc_id(&mut self) -> u32 {
+        assert!(
+            self.lookup.len() <= u32::MAX as usize,
+            "All channel ids are in use"
+        );
+        loop {
+            let id = self.next_id;
+            self.next_id = self.next_id.wrapping_add(1);
+            if !self.lookup.contains_key(&id) {
+                return id;
+            }
         }
     }
 
-    pub fn add_receiver(&mut self, priority: P, receiver: DynReceiver<T>) {
-        debug_assert!(self.lookup.contains_key(&receiver.id) == false);
+    /// Adds the receiver to the group of the given priority.
+    /// Returns the ready bit of the receiver, for the sender.
+    pub fn add_receiver(&mut self, priority: P, receiver: DynReceiver<T>) -> Arc<ReadyBit> {
+        let (group_idx, inner_idx) = self.insert_receiver(priority, receiver, true);
+        let group = &self.groups[group_idx];
+        let receiver = &group.receivers[inner_idx];
+        self.subscribers.retain(|subscriber| {
+            subscriber(ChannelEvent::Added {
+                id: receiver.id,
+                priority: &group.priority,
+            })
+        });
+        receiver.ready.clone()
+    }
+
+    /// Moves the receiver with the given id to the group of the given priority, keeping its buffered messages
+    /// and frozen state. Returns false if it doesn't exist.
+    fn move_receiver(&mut self, id: u32, priority: P) -> bool {
+        let Some(receiver) = self.detach_receiver(id).unwrap() else {
+            return false;
+        };
+        let (group_idx, inner_idx) = self.insert_receiver(priority, receiver, false);
+        // The channel may have messages, which its sender announced in the old group
+        let group = &self.groups[group_idx];
+        group.ready.set(group.receivers[inner_idx].slot);
+        true
+    }
+
+    /// Inserts the receiver into the group of the given priority, creating the group if needed,
+    /// and returns its (group_idx, inner_idx).
+    fn insert_receiver(
+        &mut self,
+        priority: P,
+        mut receiver: DynReceiver<T>,
+        apply_frozen_default: bool,
+    ) -> (usize, usize) {
+        debug_assert!(!self.lookup.contains_key(&receiver.id));
         let channel_id = receiver.id;
         let group_idx;
         let inner_idx;
         match self.groups.binary_search_by(|g| g.priority.cmp(&priority)) {
             Ok(idx) => {
-                self.groups[idx].receivers.push(receiver);
+                let group = &mut self.groups[idx];
+                if group.frozen_default && apply_frozen_default {
+                    *receiver.frozen.get_mut() = true;
+                }
+                receiver.slot = group.alloc_slot(group.receivers.len());
+                group.receivers.push(receiver);
+                group.weights.take();
                 group_idx = idx;
-                inner_idx = self.groups[idx].receivers.len() - 1;
+                inner_idx = group.receivers.len() - 1;
             }
             Err(idx) => {
-                let mut group = PriorityGroup::new(priority);
+                let (weight, frozen) = self
+                    .tier_defaults
+                    .as_ref()
+                    .map_or((1, false), |defaults| defaults(&priority));
+                let policy = self.group_policies.get(&priority).copied();
+                let mut group = PriorityGroup::new(priority, weight, frozen && self.freezing);
+                group.policy = policy;
+                if group.frozen_default && apply_frozen_default {
+                    *receiver.frozen.get_mut() = true;
+                }
+                receiver.slot = group.alloc_slot(0);
                 group.receivers.push(receiver);
+                group.key = match self.free_keys.pop() {
+                    Some(key) => key,
+                    None => {
+                        self.positions.push(idx);
+                        self.positions.len() - 1
+                    }
+                };
                 self.groups.insert(idx, group);
+                self.reindex_groups(GroupChange::Inserted(idx));
+                self.attach_tier(idx);
                 group_idx = idx;
                 inner_idx = 0;
-                // Adjust lookup
-                for group in &self.groups[idx + 1..] {
-                    for receiver in &group.receivers {
-                        let (group_idx, _) = self.lookup.get_mut(&receiver.id).unwrap();
-                        *group_idx += 1;
-                    }
-                }
             }
         }
-        self.lookup.insert(channel_id, (group_idx, inner_idx));
+        let key = self.groups[group_idx].key;
+        self.lookup.insert(channel_id, (key, inner_idx));
+        let group = &self.groups[group_idx];
+        let receiver = &group.receivers[inner_idx];
+        receiver.ready.relocate(group.ready.clone(), receiver.slot);
+        (group_idx, inner_idx)
+    }
+
+    /// Removes the receiver with the given id, returning None if it doesn't exist.
+    /// The receiver should be dropped after the state lock is released.
+    pub fn remove_receiver(&mut self, id: u32) -> Option<DynReceiver<T>> {
+        self.try_remove_receiver(id).unwrap()
+    }
+
+    /// Removes the receiver with the given id, returning None if it doesn't exist.
+    /// Returns an error instead of panicking if the lookup table is inconsistent.
+    fn try_remove_receiver(&mut self, id: u32) -> Result<Option<DynReceiver<T>>, RecvError> {
+        let removed = self.detach_receiver(id)?;
+        if let Some(receiver) = &removed {
+            if let Some(name) = &receiver.name {
+                self.names.remove(name);
+            }
+            self.subscribers
+                .retain(|subscriber| subscriber(ChannelEvent::Removed { id: receiver.id }));
+            // Drop all links involving the channel
+            self.links.remove(&id);
+            self.links.retain(|_, prerequisites| {
+                prerequisites.retain(|from| *from != id);
+                !prerequisites.is_empty()
+            });
+        }
+        Ok(removed)
     }
 
-    pub fn remove_receiver(&mut self, id: u32) {
-        let (group_idx, inner_idx) = self.lookup.remove(&id).unwrap();
-        self.groups[group_idx].receivers.remove(inner_idx);
+    /// Takes the receiver with the given id out of its group, removing the group if it becomes empty.
+    /// Links involving the channel are kept.
+    fn detach_receiver(&mut self, id: u32) -> Result<Option<DynReceiver<T>>, RecvError> {
+        let Some(&(key, inner_idx)) = self.lookup.get(&id) else {
+            return Ok(None);
+        };
+        let group_idx = self.positions.get(key).copied().ok_or(RecvError::Internal(
+            "lookup points to a missing priority group",
+        ))?;
+        let group = self.groups.get_mut(group_idx).ok_or(RecvError::Internal(
+            "lookup points to a missing priority group",
+        ))?;
+        if group.receivers.get(inner_idx).map(|r| r.id) != Some(id) {
+            return Err(RecvError::Internal("lookup points to the wrong channel"));
+        }
+        self.lookup.remove(&id);
+        let removed = group.receivers.remove(inner_idx);
+        group.free_slot(removed.slot);
+        group.weights.take();
         // Adjust lookup
-        for receiver in &self.groups[group_idx].receivers[inner_idx..] {
-            let (_, inner_idx) = self.lookup.get_mut(&receiver.id).unwrap();
+        for receiver in &group.receivers[inner_idx..] {
+            let (_, inner_idx) = self
+                .lookup
+                .get_mut(&receiver.id)
+                .ok_or(RecvError::Internal("channel is missing from lookup"))?;
             *inner_idx -= 1;
+            group.slots[receiver.slot] = Some(*inner_idx);
         }
         // Remove group if empty
-        if self.groups[group_idx].receivers.is_empty() {
-            self.groups.remove(group_idx);
+        if group.receivers.is_empty() {
+            let group = self.groups.remove(group_idx);
+            group.ready.relocate_tier(None);
+            self.free_keys.push(group.key);
+            self.reindex_groups(GroupChange::Removed(group_idx));
+        }
+        Ok(Some(removed))
+    }
+
+    /// Moves every group to the priority computed by `f`, merging groups that end up with the same priority.
+    /// A merged group keeps the tier defaults of the group with the highest old priority.
+    fn remap_priorities(&mut self, f: impl Fn(&P) -> P) {
+        let mut groups: Vec<PriorityGroup<T, P>> = Vec::with_capacity(self.groups.len());
+        for mut group in std::mem::take(&mut self.groups) {
+            group.priority = f(&group.priority);
+            match groups.binary_search_by(|g| g.priority.cmp(&group.priority)) {
+                Ok(idx) => groups[idx].absorb(group),
+                Err(idx) => groups.insert(idx, group),
+            }
+        }
+        self.groups = groups;
+        // Absorbed groups gave up their keys, so start over
+        self.positions.clear();
+        self.free_keys.clear();
+        for (group_idx, group) in self.groups.iter_mut().enumerate() {
+            group.key = group_idx;
+            self.positions.push(group_idx);
+        }
+        self.reindex_groups(GroupChange::Rebuilt);
+        for group_idx in 0..self.groups.len() {
+            self.attach_tier(group_idx);
+        }
+        self.pinned = self.pinned.as_ref().map(&f);
+        // Like the tier defaults, the policy of the highest old priority wins
+        let mut group_policies = BTreeMap::new();
+        for (priority, policy) in std::mem::take(&mut self.group_policies) {
+            group_policies.entry(f(&priority)).or_insert(policy);
+        }
+        self.group_policies = group_policies;
+        for group in &mut self.groups {
+            group.policy = self.group_policies.get(&group.priority).copied();
+        }
+        self.lookup.clear();
+        for group in &self.groups {
+            for (inner_idx, receiver) in group.receivers.iter().enumerate() {
+                self.lookup.insert(receiver.id, (group.key, inner_idx));
+            }
         }
     }
 
@@ -156,235 +385,4228 @@ impl<T, P: Priority> DynState<T, P> {
         self.groups.is_empty()
     }
 
-    fn set_frozen(&mut self, id: u32, frozen: bool) {
-        let (group_idx, inner_idx) = self.lookup.get(&id).unwrap();
-        self.groups[*group_idx].receivers[*inner_idx].frozen = frozen;
+    /// Updates the positions and tier bits of the groups moved by `change` to their current index.
+    fn reindex_groups(&mut self, change: GroupChange) {
+        let mut layout = recover(self.ready_tiers.layout.write());
+        layout.grow(self.groups.len() + 1);
+        layout.positions.resize(self.positions.len(), 0);
+        let from = match change {
+            GroupChange::Inserted(group_idx) => {
+                layout.shift(group_idx, true);
+                group_idx
+            }
+            GroupChange::Removed(group_idx) => {
+                layout.shift(group_idx, false);
+                group_idx
+            }
+            GroupChange::Rebuilt => {
+                for (group_idx, group) in self.groups.iter().enumerate() {
+                    layout.store(group_idx, !group.ready.is_empty());
+                }
+                layout.clear_from(self.groups.len());
+                0
+            }
+        };
+        for (group_idx, group) in self.groups.iter().enumerate().skip(from) {
+            self.positions[group.key] = group_idx;
+            layout.positions[group.key] = group_idx;
+        }
     }
-}
 
-struct PriorityGroup<T, P: Priority> {
-    priority: P,
-    receivers: Vec<DynReceiver<T>>,
-}
+    /// Lets the group at the given index keep its tier bit in sync, once it got its key.
+    fn attach_tier(&self, group_idx: usize) {
+        let group = &self.groups[group_idx];
+        group
+            .ready
+            .relocate_tier(Some((self.ready_tiers.clone(), group.key)));
+    }
 
-impl<T, P: Priority> PriorityGroup<T, P> {
-    fn new(priority: P) -> Self {
-        Self {
-            priority,
-            receivers: Vec::new(),
+    /// Like scan_order(), but only yields groups with their tier bit set, if there is no pinned group
+    /// and no promotions. Then the groups without a ready channel are skipped without looking at them.
+    fn ready_scan_order<'a>(
+        &'a self,
+        promotions: &[(u32, usize)],
+    ) -> impl Iterator<Item = (usize, &'a PriorityGroup<T, P>)> {
+        if self.pinned.is_some() || !promotions.is_empty() {
+            return Either::Left(self.scan_order());
+        }
+        let layout = recover(self.ready_tiers.layout.read());
+        let mut word_idx = 0;
+        let mut bits = 0u64;
+        Either::Right(std::iter::from_fn(move || loop {
+            if bits == 0 {
+                bits = layout.words.get(word_idx)?.load(Ordering::SeqCst);
+                word_idx += 1;
+                continue;
+            }
+            let group_idx = (word_idx - 1) * 64 + bits.trailing_zeros() as usize;
+            bits &= bits - 1;
+            if let Some(group) = self.groups.get(group_idx) {
+                return Some((group_idx, group));
+            }
+        }))
+    }
+
+    /// Iterates over the groups in the order receive() considers them:
+    /// the pinned group first, if any, then all others from highest to lowest priority.
+    fn scan_order(&self) -> impl Iterator<Item = (usize, &PriorityGroup<T, P>)> {
+        let pinned_idx = self
+            .pinned
+            .as_ref()
+            .and_then(|p| self.groups.binary_search_by(|g| g.priority.cmp(p)).ok());
+        let rest = self
+            .groups
+            .iter()
+            .enumerate()
+            .filter(move |(idx, _)| Some(*idx) != pinned_idx);
+        pinned_idx
+            .map(|idx| (idx, &self.groups[idx]))
+            .into_iter()
+            .chain(rest)
+    }
+
+    /// Returns the (group_idx, inner_idx) of the channel with the given id.
+    fn locate(&self, id: u32) -> Option<(usize, usize)> {
+        let &(key, inner_idx) = self.lookup.get(&id)?;
+        Some((*self.positions.get(key)?, inner_idx))
+    }
+
+    fn receiver(&self, id: u32) -> Option<&DynReceiver<T>> {
+        let (group_idx, inner_idx) = self.locate(id)?;
+        Some(&self.groups[group_idx].receivers[inner_idx])
+    }
+
+    /// Returns the index of the group the channel is scanned in: its own group, or the group of
+    /// a prerequisite with buffered messages if that has a higher priority.
+    fn effective_group(&self, id: u32) -> Result<usize, RecvError> {
+        let own = self.group_of(id)?;
+        self.links
+            .get(&id)
+            .into_iter()
+            .flatten()
+            .filter(|from| self.receiver(**from).is_some_and(|r| !r.is_empty()))
+            .try_fold(own, |group_idx, from| {
+                Ok(group_idx.min(self.effective_group(*from)?))
+            })
+    }
+
+    /// Returns the index of the group of a linked channel, which must exist.
+    fn group_of(&self, id: u32) -> Result<usize, RecvError> {
+        let &(key, _) = self.lookup.get(&id).ok_or(RecvError::Internal(
+            "a linked channel is missing from the lookup",
+        ))?;
+        self.positions.get(key).copied().ok_or(RecvError::Internal(
+            "lookup points to a missing priority group",
+        ))
+    }
+
+    /// Returns (id, group_idx) of every linked channel that is currently promoted to a higher priority group.
+    fn promotions(&self) -> Result<SmallVec<[(u32, usize); 4]>, RecvError> {
+        let mut promotions = SmallVec::new();
+        for &to in self.links.keys() {
+            let group_idx = self.effective_group(to)?;
+            if group_idx < self.group_of(to)? {
+                promotions.push((to, group_idx));
+            }
         }
+        Ok(promotions)
     }
-}
 
-#[derive(Debug, Error)]
-pub enum SendError {
-    #[error("The channel receiver is disconnected")]
-    Disconnected,
+    /// Returns true if receive() would find an unfrozen channel with a message in the group,
+    /// taking the given promotions into account.
+    fn has_deliverable(&self, group_idx: usize, promotions: &[(u32, usize)]) -> bool {
+        // Without freezing no channel can be frozen, so only the buffer is looked at
+        let deliverable =
+            |r: &DynReceiver<T>| (!self.freezing || !r.is_frozen()) && r.has_message();
+        let own = self.groups[group_idx]
+            .receivers
+            .iter()
+            .filter(|r| promotions.iter().all(|(id, _)| *id != r.id))
+            .any(deliverable);
+        own || promotions
+            .iter()
+            .filter(|(_, idx)| *idx == group_idx)
+            .any(|(id, _)| self.receiver(*id).is_some_and(deliverable))
+    }
+
+    /// Returns true if `to` is a direct or indirect prerequisite of `from`.
+    fn depends_on(&self, from: u32, to: u32) -> bool {
+        self.links
+            .get(&from)
+            .into_iter()
+            .flatten()
+            .any(|prerequisite| *prerequisite == to || self.depends_on(*prerequisite, to))
+    }
+
+    fn group(&self, priority: &P) -> Option<&PriorityGroup<T, P>> {
+        self.groups
+            .binary_search_by(|g| g.priority.cmp(priority))
+            .ok()
+            .map(|idx| &self.groups[idx])
+    }
+
+    /// Panics if `frozen` is true and freezing was disabled with without_freezing().
+    /// Must be called before taking the write lock, so the panic doesn't poison it.
+    fn check_freezing(&self, frozen: bool) {
+        assert!(
+            self.freezing || !frozen,
+            "Freezing is disabled for this multichannel"
+        );
+    }
+
+    /// Returns the amount of buffered messages that became deliverable by unfreezing the channel,
+    /// or None if it doesn't exist. Only needs the read lock, so receivers aren't blocked meanwhile.
+    fn try_set_frozen(&self, id: u32, frozen: bool) -> Option<usize> {
+        let (group_idx, inner_idx) = self.locate(id)?;
+        let group = &self.groups[group_idx];
+        let receiver = &group.receivers[inner_idx];
+        let thawed = if receiver.frozen.swap(frozen, Ordering::SeqCst) && !frozen {
+            receiver.len()
+        } else {
+            0
+        };
+        if !frozen {
+            group.ready.set(receiver.slot);
+        }
+        Some(thawed)
+    }
 }
 
-pub struct DynSender<T, P: Priority> {
-    id: u32,
-    count_multireceivers: Arc<AtomicUsize>,
-    condvar: Arc<(Mutex<usize>, Condvar)>,
-    state: Arc<RwLock<DynState<T, P>>>,
-    inner: crossbeam_channel::Sender<T>,
+/// A bitmap with one bit per channel slot, which is set whenever the channel may have a deliverable message.
+/// This lets receive() skip empty channels without looking at them, which matters for many mostly idle channels.
+/// Its locks only guard the layout of the map, so a poisoned one is recovered from.
+struct ReadyMap {
+    // Only locked for writing to grow the map
+    words: RwLock<Vec<AtomicU64>>,
+    // Amount of set bits, so receive() can skip a group without a ready channel at once
+    count: AtomicUsize,
+    // The tier map and key of the group, its tier bit is set while the count is greater than 0
+    tier: RwLock<Option<(Arc<TierMap>, usize)>>,
 }
 
-impl<T, P: Priority> DynSender<T, P> {
-    pub fn id(&self) -> u32 {
-        self.id
+impl ReadyMap {
+    fn new() -> Self {
+        Self {
+            words: RwLock::new(Vec::new()),
+            count: AtomicUsize::new(0),
+            tier: RwLock::new(None),
+        }
     }
 
-    fn wake_receiver(&self) {
-        let (lock, condvar) = &*self.condvar;
-        {
-            let mut count = lock.lock().unwrap();
-            *count += 1;
+    fn grow(&self, slots: usize) {
+        let mut words = recover(self.words.write());
+        while words.len() * 64 < slots {
+            words.push(AtomicU64::new(0));
         }
-        condvar.notify_one();
     }
 
-    pub fn send(&self, value: T) -> Result<(), SendError> {
-        if self.count_multireceivers.load(Ordering::Relaxed) == 0 {
-            return Err(SendError::Disconnected);
+    fn set(&self, slot: usize) {
+        self.set_bit(&recover(self.words.read())[slot / 64], 1 << (slot % 64));
+    }
+
+    fn clear(&self, slot: usize) {
+        self.clear_bit(&recover(self.words.read())[slot / 64], 1 << (slot % 64));
+    }
+
+    fn set_bit(&self, word: &AtomicU64, bit: u64) {
+        // Counted before the bit becomes visible, so a receiver seeing the bit also sees the count
+        if self.count.fetch_add(1, Ordering::SeqCst) == 0 {
+            if let Some((tiers, key)) = &*recover(self.tier.read()) {
+                tiers.set(*key);
+            }
         }
-        if self.inner.capacity() == Some(0) {
-            self.wake_receiver();
+        if word.fetch_or(bit, Ordering::SeqCst) & bit != 0 {
+            self.uncount();
         }
-        if self.inner.send(value).is_err() {
-            return Err(SendError::Disconnected);
+    }
+
+    fn clear_bit(&self, word: &AtomicU64, bit: u64) {
+        if word.fetch_and(!bit, Ordering::SeqCst) & bit != 0 {
+            self.uncount();
         }
-        if !(self.inner.capacity() == Some(0)) {
-            self.wake_receiver();
+    }
+
+    fn uncount(&self) {
+        if self.count.fetch_sub(1, Ordering::SeqCst) == 1 {
+            if let Some((tiers, key)) = &*recover(self.tier.read()) {
+                tiers.clear_unless(*key, || !self.is_empty());
+            }
         }
-        Ok(())
     }
 
-    pub fn set_frozen(&self, frozen: bool) {
-        let mut state = self.state.write().unwrap();
-        state.set_frozen(self.id, frozen);
+    /// Points this group map to its tier bit, given by the tier map and the key of the group, or to nothing,
+    /// and syncs the bit with the count.
+    fn relocate_tier(&self, tier: Option<(Arc<TierMap>, usize)>) {
+        let mut location = recover(self.tier.write());
+        *location = tier;
+        if let Some((tiers, key)) = &*location {
+            tiers.clear_unless(*key, || !self.is_empty());
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.count.load(Ordering::SeqCst) == 0
     }
 }
 
-struct DynReceiver<T> {
-    id: u32,
-    weight: u32,
-    frozen: bool,
-    inner: crossbeam_channel::Receiver<T>,
+/// The tier bitmap, with the bit of every priority group at its index, set while the group has a ready channel.
+/// The groups refer to their bit by their stable key, so inserting or removing a group only rewrites the bits
+/// of the later groups in place, instead of pointing every later group to its new bit.
+struct TierMap {
+    // Locked for writing while groups move, so no bit is set or cleared at a stale index
+    layout: RwLock<TierLayout>,
 }
 
-pub struct DynMultiReceiver<T, P: Priority> {
-    amount_multireceivers: Arc<AtomicUsize>,
-    cleanup: Arc<(AtomicBool, Mutex<HashSet<u32>>)>,
-    condvar: Arc<(Mutex<usize>, Condvar)>,
-    state: Arc<RwLock<DynState<T, P>>>,
+struct TierLayout {
+    positions: Vec<usize>, // group key -> group_idx
+    words: Vec<AtomicU64>,
 }
 
-impl<T, P: Priority> Clone for DynMultiReceiver<T, P> {
-    fn clone(&self) -> Self {
-        self.amount_multireceivers.fetch_add(1, Ordering::Relaxed);
+/// How the sorted groups changed, for DynState::reindex_groups().
+enum GroupChange {
+    Inserted(usize),
+    Removed(usize),
+    // The groups were sorted from scratch
+    Rebuilt,
+}
+
+impl TierMap {
+    fn new() -> Self {
         Self {
-            amount_multireceivers: self.amount_multireceivers.clone(),
-            cleanup: self.cleanup.clone(),
-            condvar: self.condvar.clone(),
-            state: self.state.clone(),
+            layout: RwLock::new(TierLayout {
+                positions: Vec::new(),
+                words: Vec::new(),
+            }),
         }
     }
-}
 
-impl<T, P: Priority> Drop for DynMultiReceiver<T, P> {
-    fn drop(&mut self) {
-        self.amount_multireceivers.fetch_sub(1, Ordering::Relaxed);
+    fn grow(&self, slots: usize) {
+        recover(self.layout.write()).grow(slots);
+    }
+
+    fn set(&self, key: usize) {
+        let layout = recover(self.layout.read());
+        layout.store(layout.positions[key], true);
+    }
+
+    /// Clears the bit of the group with the given key, and sets it again if `ready` returns true afterwards,
+    /// so a concurrent send that counted in the meantime isn't missed.
+    fn clear_unless(&self, key: usize, ready: impl Fn() -> bool) {
+        let layout = recover(self.layout.read());
+        let group_idx = layout.positions[key];
+        layout.store(group_idx, false);
+        if ready() {
+            layout.store(group_idx, true);
+        }
     }
 }
 
-impl<T, P: Priority> DynMultiReceiver<T, P> {
-    pub fn new() -> Self {
-        Self {
-            amount_multireceivers: Arc::new(AtomicUsize::new(1)),
-            cleanup: Arc::new((AtomicBool::new(false), Mutex::new(HashSet::default()))),
-            condvar: Arc::new((Mutex::new(0), Condvar::new())),
-            state: Arc::new(RwLock::new(DynState::new())),
+impl TierLayout {
+    fn grow(&mut self, slots: usize) {
+        while self.words.len() * 64 < slots {
+            self.words.push(AtomicU64::new(0));
         }
     }
 
-    /// Create a new channel with the given priority, weight, frozen state and optional bounds.
-    /// 
-    /// The weight is used to determine the probability of the channel being selected when calling receive()
-    /// on the DynMultiReceiver. The weight is relative to the weights of other channels in the same priority group.
-    /// The weight must be greater than 0.
-    /// 
-    /// The frozen state determines if the channel is considered when calling receive() on the DynMultiReceiver.
-    /// If the channel is frozen, it will not be considered, even if it has a message.
-    /// 
-    /// The bounds parameter is used to create a bounded channel. If None is passed, an unbounded channel is created.
-    /// If Some(bounds) is passed, a bounded channel with the given bounds is created.
-    pub fn new_channel(
-        &self,
-        priority: P,
-        weight: u32,
-        frozen: bool,
-        bounds: Option<usize>,
-    ) -> DynSender<T, P> {
-        assert!(weight > 0, "Weight must be greater than 0");
-        let (sender, receiver) = match bounds {
-            Some(bounds) => crossbeam_channel::bounded(bounds),
-            None => crossbeam_channel::unbounded(),
+    fn store(&self, group_idx: usize, ready: bool) {
+        let word = &self.words[group_idx / 64];
+        let bit = 1 << (group_idx % 64);
+        match ready {
+            true => word.fetch_or(bit, Ordering::SeqCst),
+            false => word.fetch_and(!bit, Ordering::SeqCst),
         };
-        let id;
-        {
-            let mut state = self.state.write().unwrap();
-            id = state.next_id;
-            state.next_id += 1;
-            let receiver = DynReceiver {
-                id,
-                weight,
-                frozen,
-                inner: receiver,
+    }
+
+    /// Moves the bits of the groups from `group_idx` on by one, up after a group was inserted there,
+    /// leaving its bit cleared, or down after the group there was removed.
+    fn shift(&mut self, group_idx: usize, up: bool) {
+        let first = group_idx / 64;
+        // The bits of the earlier groups in the first word stay in place
+        let kept = (1u64 << (group_idx % 64)) - 1;
+        let words = &mut self.words[first..];
+        if up {
+            for word_idx in (0..words.len()).rev() {
+                let carry = match word_idx {
+                    0 => 0,
+                    _ => *words[word_idx - 1].get_mut() >> 63,
+                };
+                let word = words[word_idx].get_mut();
+                *word = match word_idx {
+                    0 => ((*word & !kept) << 1) | (*word & kept),
+                    _ => (*word << 1) | carry,
+                };
+            }
+        } else {
+            for word_idx in 0..words.len() {
+                let carry = match words.get_mut(word_idx + 1) {
+                    Some(next) => *next.get_mut() & 1,
+                    None => 0,
+                };
+                let word = words[word_idx].get_mut();
+                *word = match word_idx {
+                    0 => ((*word >> 1) & !kept) | (*word & kept),
+                    _ => *word >> 1,
+                } | (carry << 63);
+            }
+        }
+    }
+
+    /// Clears the bits of the groups from `group_idx` on, which were removed.
+    fn clear_from(&self, group_idx: usize) {
+        for (word_idx, word) in self.words.iter().enumerate().skip(group_idx / 64) {
+            let kept = match word_idx == group_idx / 64 {
+                true => (1 << (group_idx % 64)) - 1,
+                false => 0,
             };
-            state.add_receiver(priority, receiver);
+            word.fetch_and(kept, Ordering::SeqCst);
         }
-        DynSender {
-            id,
-            count_multireceivers: self.amount_multireceivers.clone(),
-            condvar: self.condvar.clone(),
-            state: self.state.clone(),
-            inner: sender,
+    }
+}
+
+/// The location of the ready bit of a channel, shared by its sender and receiver.
+/// It changes when the channel moves to another group.
+struct ReadyBit {
+    location: RwLock<(Arc<ReadyMap>, usize)>,
+}
+
+impl ReadyBit {
+    /// Points to a map of its own until the receiver is added to a group.
+    fn new() -> Self {
+        Self {
+            location: RwLock::new((Arc::new(ReadyMap::new()), 0)),
         }
     }
 
-    pub fn remove_channel_by_id(&self, id: u32) {
-        self.state.write().unwrap().remove_receiver(id);
+    fn set(&self) {
+        let (map, slot) = &*recover(self.location.read());
+        map.set(*slot);
     }
 
-    pub fn remove_channel(&self, sender: &DynSender<T, P>) {
-        self.remove_channel_by_id(sender.id);
+    fn relocate(&self, map: Arc<ReadyMap>, slot: usize) {
+        *recover(self.location.write()) = (map, slot);
     }
+}
 
-    pub fn receive(&self) -> T {
-        if self.cleanup.0.fetch_and(false, Ordering::Relaxed) {
-            let mut state = self.state.write().unwrap();
-            let mut to_clean = self.cleanup.1.lock().unwrap();
-            for id in to_clean.drain() {
-                state.remove_receiver(id);
-            }
+/// A snapshot of the frozen state of every channel, taken with [`DynMultiReceiver::freeze_snapshot`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FrozenStates {
+    states: HashMap<u32, bool>,
+}
+
+impl FrozenStates {
+    /// Returns the captured frozen state of the channel with the given id,
+    /// or None if the channel did not exist when the snapshot was taken.
+    pub fn get(&self, id: u32) -> Option<bool> {
+        self.states.get(&id).copied()
+    }
+
+    /// Iterates over all captured (id, frozen) pairs in no particular order.
+    pub fn iter(&self) -> impl Iterator<Item = (u32, bool)> + '_ {
+        self.states.iter().map(|(id, frozen)| (*id, *frozen))
+    }
+
+    pub fn len(&self) -> usize {
+        self.states.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.states.is_empty()
+    }
+}
+
+/// The metadata of a channel, taken with [`DynMultiReceiver::channels`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChannelInfo<P> {
+    pub id: u32,
+    pub priority: P,
+    pub weight: u32,
+    pub frozen: bool,
+    /// The amount of buffered messages, like DynSender::len(), but including a message
+    /// receive_oldest() already looked at.
+    pub queued: usize,
+}
+
+/// A change of the channel set, delivered to the subscribers of [`DynMultiReceiver::events`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ChannelEvent<P> {
+    /// A channel was created.
+    Added { id: u32, priority: P },
+    /// A channel was removed, explicitly or after its senders were dropped.
+    Removed { id: u32 },
+}
+
+/// The message counters of a channel, taken with [`DynMultiReceiver::stats`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ChannelStats {
+    /// Messages sent into the channel since it was created.
+    pub sent: u64,
+    /// Messages taken out of the channel by any receive.
+    pub received: u64,
+    /// The amount of buffered messages, like DynSender::len(), but including a message
+    /// receive_oldest() already looked at.
+    pub current_len: usize,
+}
+
+struct PriorityGroup<T, P: Priority> {
+    priority: P,
+    // Assigned once the group is added to the state
+    key: usize,
+    // Tier defaults, see with_tier_defaults()
+    weight: u32,
+    frozen_default: bool,
+    receivers: Vec<DynReceiver<T>>,
+    // Virtual time of the group for size fairness
+    virtual_clock: AtomicU64,
+    // Set with set_group_policy(), overrides the policy of the multichannel
+    policy: Option<Policy>,
+    // The lowest channel id served next by the round robin policy
+    cursor: AtomicU32,
+    ready: Arc<ReadyMap>,
+    slots: Vec<Option<usize>>, // slot -> inner_idx
+    free_slots: Vec<usize>,
+    // Cumulative channel weights by inner_idx, built on the first weighted pick after a channel or weight changed
+    weights: OnceLock<Box<[u64]>>,
+}
+
+impl<T, P: Priority> PriorityGroup<T, P> {
+    fn new(priority: P, weight: u32, frozen_default: bool) -> Self {
+        Self {
+            priority,
+            key: 0,
+            weight,
+            frozen_default,
+            receivers: Vec::new(),
+            virtual_clock: AtomicU64::new(0),
+            policy: None,
+            cursor: AtomicU32::new(0),
+            ready: Arc::new(ReadyMap::new()),
+            weights: OnceLock::new(),
+            slots: Vec::new(),
+            free_slots: Vec::new(),
         }
+    }
 
-        let (lock, condvar) = &*self.condvar;
-        {
-            let mut count = lock.lock().unwrap();
-            while *count == 0 {
-                count = condvar.wait(count).unwrap();
+    /// Releases the spare capacity of the receivers and slots. Free slots at the end are dropped.
+    fn shrink_to_fit(&mut self) {
+        while self.slots.last() == Some(&None) {
+            self.slots.pop();
+        }
+        let slots = self.slots.len();
+        self.free_slots.retain(|&slot| slot < slots);
+        self.slots.shrink_to_fit();
+        self.free_slots.shrink_to_fit();
+        self.receivers.shrink_to_fit();
+    }
+
+    /// Allocates a slot in the ready map for the receiver at the given index.
+    fn alloc_slot(&mut self, inner_idx: usize) -> usize {
+        match self.free_slots.pop() {
+            Some(slot) => {
+                self.slots[slot] = Some(inner_idx);
+                slot
+            }
+            None => {
+                self.slots.push(Some(inner_idx));
+                self.ready.grow(self.slots.len());
+                self.slots.len() - 1
             }
-            *count -= 1;
         }
-        let state = self.state.read().unwrap();
+    }
 
-        // Find the highest priority group with a receiver that has a message
-        let mut candidate_weights = SmallVec::<[u32; 8]>::new();
-        let mut candidate_indices = SmallVec::<[usize; 8]>::new();
-        loop {
-            for group in &state.groups {
-                candidate_indices.clear();
-                candidate_weights.clear();
-                for i in 0..group.receivers.len() {
-                    let receiver = &group.receivers[i];
-                    if (receiver.inner.len() > 0 && !receiver.frozen)
-                        || (receiver.inner.capacity() == Some(0))
-                    {
-                        candidate_indices.push(i);
-                        candidate_weights.push(receiver.weight);
+    /// Returns the cumulative weights of the channels, in the order of their inner_idx.
+    fn cumulative_weights(&self) -> &[u64] {
+        self.weights.get_or_init(|| {
+            let mut total = 0;
+            self.receivers
+                .iter()
+                .map(|receiver| {
+                    total += receiver.weight as u64;
+                    total
+                })
+                .collect()
+        })
+    }
+
+    /// Moves all receivers of `other` into this group.
+    fn absorb(&mut self, other: PriorityGroup<T, P>) {
+        other.ready.relocate_tier(None);
+        self.weights.take();
+        for mut receiver in other.receivers {
+            receiver.slot = self.alloc_slot(self.receivers.len());
+            receiver.ready.relocate(self.ready.clone(), receiver.slot);
+            // The sender may have set its bit in the old map in the meantime
+            self.ready.set(receiver.slot);
+            self.receivers.push(receiver);
+        }
+    }
+
+    fn free_slot(&mut self, slot: usize) {
+        self.slots[slot] = None;
+        self.free_slots.push(slot);
+        self.ready.clear(slot);
+    }
+
+    /// Returns the receivers that may have a deliverable message, by looking only at those with their ready bit set.
+    /// Clears the ready bit of receivers that turned out to be empty or frozen.
+    /// The positions are pushed as (group_idx, inner_idx), with `group_idx` being the index of this group.
+    /// Without `freezing`, the frozen flags aren't looked at.
+    fn ready_receivers(
+        &self,
+        group_idx: usize,
+        freezing: bool,
+        ready: &mut SmallVec<[(usize, usize); 8]>,
+    ) -> Result<(), RecvError> {
+        let deliverable = |receiver: &DynReceiver<T>| {
+            (!freezing || !receiver.is_frozen()) && receiver.has_message()
+        };
+        if self.ready.is_empty() {
+            return Ok(());
+        }
+        let words = self.ready.words.read()?;
+        for (word_idx, word) in words.iter().enumerate() {
+            let mut bits = word.load(Ordering::SeqCst);
+            while bits != 0 {
+                let slot = word_idx * 64 + bits.trailing_zeros() as usize;
+                let bit = 1 << bits.trailing_zeros();
+                bits &= !bit;
+                let Some(inner_idx) = self.slots[slot] else {
+                    self.ready.clear_bit(word, bit);
+                    continue;
+                };
+                let receiver = &self.receivers[inner_idx];
+                if !deliverable(receiver) {
+                    // Clear before checking again, so a concurrent send or unfreeze can't be missed
+                    self.ready.clear_bit(word, bit);
+                    if !deliverable(receiver) {
+                        continue;
                     }
+                    self.ready.set_bit(word, bit);
                 }
-                while !candidate_indices.is_empty() {
-                    let dist = WeightedIndex::new(&candidate_weights).unwrap();
-                    let candidate_index = dist.sample(&mut rand::thread_rng());
-                    let idx = candidate_indices[candidate_index];
-                    match group.receivers[idx].inner.try_recv() {
-                        Ok(value) => return value,
-                        Err(crossbeam_channel::TryRecvError::Empty) => {
-                            candidate_indices.remove(candidate_index);
-                            candidate_weights.remove(candidate_index);
-                            continue;
-                        }
-                        Err(crossbeam_channel::TryRecvError::Disconnected) => {
-                            self.cleanup
-                                .1
-                                .lock()
-                                .unwrap()
-                                .insert(group.receivers[idx].id);
-                            self.cleanup.0.store(true, Ordering::Relaxed);
-                            candidate_indices.remove(candidate_index);
-                            candidate_weights.remove(candidate_index);
-                            continue;
-                        }
-                    };
-                }
+                ready.push((group_idx, inner_idx));
             }
         }
+        Ok(())
     }
 
-    pub fn no_channels(&self) -> bool {
-        self.state.read().unwrap().is_empty()
+    /// Accounts a message of the given size to a receiver of this group.
+    fn charge(&self, receiver: &DynReceiver<T>, size: u64) {
+        // A channel that was idle for a while starts at the group clock, so it can't hog the group to catch up
+        let start = receiver
+            .served
+            .load(Ordering::Relaxed)
+            .max(self.virtual_clock.load(Ordering::Relaxed));
+        let cost = ((size as u128) << 16) / receiver.weight as u128;
+        let cost = cost.min(u64::MAX as u128) as u64;
+        receiver
+            .served
+            .store(start.saturating_add(cost), Ordering::Relaxed);
+        self.virtual_clock.fetch_max(start, Ordering::Relaxed);
+    }
+}
+
+/// Returns the position of the candidate that was served the least bytes relative to its weight.
+fn fairest_candidate<'a, T: 'a>(candidates: impl Iterator<Item = &'a DynReceiver<T>>) -> usize {
+    let mut fairest = 0;
+    let mut fairest_served = u64::MAX;
+    for (candidate_index, receiver) in candidates.enumerate() {
+        let served = receiver.served.load(Ordering::Relaxed);
+        if served < fairest_served {
+            fairest = candidate_index;
+            fairest_served = served;
+        }
+    }
+    fairest
+}
+
+/// Returns the position of the candidate with the most credit after every candidate earned its weight,
+/// following the smooth weighted round robin scheme.
+fn deficit_candidate<'a, T: 'a>(candidates: impl Iterator<Item = &'a DynReceiver<T>>) -> usize {
+    let mut best = 0;
+    let mut best_credit = i64::MIN;
+    for (candidate_index, receiver) in candidates.enumerate() {
+        let credit = receiver.deficit.load(Ordering::Relaxed) + receiver.weight as i64;
+        if credit > best_credit {
+            best = candidate_index;
+            best_credit = credit;
+        }
+    }
+    best
+}
+
+/// Returns the position of the candidate with the lowest id that is at least `cursor`,
+/// wrapping around to the lowest id overall.
+fn round_robin_candidate<'a, T: 'a>(
+    candidates: impl Iterator<Item = &'a DynReceiver<T>>,
+    cursor: u32,
+) -> usize {
+    candidates
+        .enumerate()
+        .min_by_key(|(_, receiver)| (receiver.id < cursor, receiver.id))
+        .map_or(0, |(candidate_index, _)| candidate_index)
+}
+
+/// Samples a candidate with a probability proportional to its weight,
+/// given the sum of the candidate weights.
+fn weighted_candidate<'a, T: 'a>(
+    candidates: impl Iterator<Item = &'a DynReceiver<T>>,
+    total_weight: u64,
+    rng: &mut impl RngCore,
+) -> Result<usize, RecvError> {
+    if total_weight == 0 {
+        return Err(RecvError::Internal("invalid channel weights"));
+    }
+    let mut point = rng.gen_range(0..total_weight);
+    for (candidate_index, receiver) in candidates.enumerate() {
+        let weight = receiver.weight as u64;
+        if point < weight {
+            return Ok(candidate_index);
+        }
+        point -= weight;
+    }
+    Err(RecvError::Internal("invalid channel weights"))
+}
+
+/// Samples an index with a probability proportional to its weight, given the cumulative weights.
+fn table_candidate(cumulative_weights: &[u64], rng: &mut impl RngCore) -> Result<usize, RecvError> {
+    let Some(&total_weight) = cumulative_weights.last().filter(|total| **total > 0) else {
+        return Err(RecvError::Internal("invalid channel weights"));
+    };
+    let point = rng.gen_range(0..total_weight);
+    Ok(cumulative_weights.partition_point(|&weight| weight <= point))
+}
+
+/// Returns a key that sorts the candidates by how `policy` would pick them, the next pick coming last.
+/// For Policy::Weighted the order is a weighted sample without replacement,
+/// which is what picking and dropping candidates one by one amounts to.
+fn pick_order_key<T>(
+    receiver: &DynReceiver<T>,
+    size_fair: bool,
+    policy: Policy,
+    cursor: u32,
+    rng: &mut impl RngCore,
+) -> u64 {
+    match (size_fair, policy) {
+        (true, _) => !receiver.served.load(Ordering::Relaxed),
+        (false, Policy::RoundRobin) => {
+            !(((receiver.id < cursor) as u64) << 32 | receiver.id as u64)
+        }
+        // A random number raised to 1 / weight, the largest one is picked first
+        (false, Policy::Weighted) => rng
+            .gen::<f64>()
+            .powf(1.0 / receiver.weight as f64)
+            .to_bits(),
+        (false, Policy::DeterministicWeighted) => {
+            let credit = receiver.deficit.load(Ordering::Relaxed) + receiver.weight as i64;
+            (credit as u64) ^ (1 << 63)
+        }
+    }
+}
+
+/// Hands every candidate its weight as credit and charges the served one the weight of all candidates.
+fn settle_deficits<'a, T: 'a>(
+    candidates: impl Iterator<Item = &'a DynReceiver<T>>,
+    served: &DynReceiver<T>,
+) {
+    let mut total = 0;
+    for receiver in candidates {
+        receiver
+            .deficit
+            .fetch_add(receiver.weight as i64, Ordering::Relaxed);
+        total += receiver.weight as i64;
+    }
+    served.deficit.fetch_sub(total, Ordering::Relaxed);
+}
+
+#[derive(Debug, Error)]
+pub enum SendError {
+    #[error("The channel receiver is disconnected")]
+    Disconnected,
+    #[error("The channel already accepted its maximum amount of messages")]
+    Exhausted,
+}
+
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum TrySendError<T> {
+    #[error("The channel is full")]
+    Full(T),
+    #[error("The channel receiver is disconnected")]
+    Disconnected(T),
+    #[error("The channel already accepted its maximum amount of messages")]
+    Exhausted(T),
+}
+
+impl<T> TrySendError<T> {
+    fn new(error: SendError, value: T) -> Self {
+        match error {
+            SendError::Disconnected => Self::Disconnected(value),
+            SendError::Exhausted => Self::Exhausted(value),
+        }
+    }
+
+    /// Returns the message that couldn't be sent.
+    pub fn into_inner(self) -> T {
+        match self {
+            Self::Full(value) | Self::Disconnected(value) | Self::Exhausted(value) => value,
+        }
+    }
+}
+
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum SendTimeoutError<T> {
+    #[error("The channel stayed full until the timeout expired")]
+    Timeout(T),
+    #[error("The channel receiver is disconnected")]
+    Disconnected(T),
+    #[error("The channel already accepted its maximum amount of messages")]
+    Exhausted(T),
+}
+
+impl<T> SendTimeoutError<T> {
+    fn new(error: SendError, value: T) -> Self {
+        match error {
+            SendError::Disconnected => Self::Disconnected(value),
+            SendError::Exhausted => Self::Exhausted(value),
+        }
+    }
+
+    /// Returns the message that couldn't be sent.
+    pub fn into_inner(self) -> T {
+        match self {
+            Self::Timeout(value) | Self::Disconnected(value) | Self::Exhausted(value) => value,
+        }
+    }
+}
+
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum TryReceiveError {
+    #[error("No channel has a deliverable message")]
+    Empty,
+}
+
+#[derive(Debug, Error, PartialEq, Eq)]
+#[error("A channel named \"{0}\" already exists")]
+pub struct NameTakenError(pub String);
+
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum TryRecvFromError {
+    #[error("The channel does not exist")]
+    NotFound,
+    #[error("The channel has no message")]
+    Empty,
+    #[error("The channel has no message and all of its senders were dropped")]
+    Disconnected,
+}
+
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum RecvTimeoutError {
+    #[error("No message became deliverable before the timeout expired")]
+    Timeout,
+    #[error("There is no channel left to receive from")]
+    Disconnected,
+}
+
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum LinkError {
+    #[error("Channel {0} does not exist")]
+    UnknownChannel(u32),
+    #[error("The link would make a channel depend on itself")]
+    Cycle,
+}
+
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum RecvError {
+    /// Never returned with the parking-lot feature, as the parking_lot locks don't poison.
+    #[error("A lock was poisoned by a thread that panicked while holding it")]
+    Poisoned,
+    #[error("The internal state is inconsistent: {0}")]
+    Internal(&'static str),
+    #[error("There is no channel left to receive from")]
+    Disconnected,
+}
+
+impl<G> From<std::sync::PoisonError<G>> for RecvError {
+    fn from(_: std::sync::PoisonError<G>) -> Self {
+        RecvError::Poisoned
+    }
+}
+
+type WatermarkFn = Arc<dyn Fn() + Send + Sync>;
+
+struct Watermark {
+    threshold: usize,
+    rising: bool,
+    callback: WatermarkFn,
+}
+
+/// The aggregate amount of buffered messages, together with the watermark callbacks observing it.
+struct Backlog {
+    // Sends are counted before their message becomes visible and receives after taking it,
+    // so this never falls below the amount of buffered messages
+    pending: AtomicIsize,
+    armed: AtomicBool,
+    watermarks: RwLock<Vec<Watermark>>,
+}
+
+impl Backlog {
+    fn new() -> Self {
+        Self {
+            pending: AtomicIsize::new(0),
+            armed: AtomicBool::new(false),
+            watermarks: RwLock::new(Vec::new()),
+        }
+    }
+
+    /// Counts a message about to be sent, without firing the watermarks yet, as the send may still fail.
+    /// Returns the backlog before the message, for committed() once it was sent, otherwise sub(1) rolls it back.
+    fn count(&self) -> isize {
+        self.pending.fetch_add(1, Ordering::Relaxed)
+    }
+
+    /// Fires the watermarks crossed by a message counted with count(), once it was sent.
+    fn committed(&self, from: isize) {
+        self.fire(from, from + 1);
+    }
+
+    fn sub(&self, amount: usize) {
+        let from = self.pending.fetch_sub(amount as isize, Ordering::Relaxed);
+        self.fire(from, from - amount as isize);
+    }
+
+    /// Calls the callbacks of all watermarks crossed by going from `from` to `to`.
+    /// Must not be called while holding the state lock.
+    fn fire(&self, from: isize, to: isize) {
+        if from == to || !self.armed.load(Ordering::Relaxed) {
+            return;
+        }
+        let callbacks: SmallVec<[WatermarkFn; 4]> = self
+            .watermarks
+            .read()
+            .unwrap()
+            .iter()
+            .filter(|w| {
+                let threshold = w.threshold as isize;
+                if w.rising {
+                    from <= threshold && to > threshold
+                } else {
+                    from >= threshold && to < threshold
+                }
+            })
+            .map(|w| w.callback.clone())
+            .collect();
+        for callback in callbacks {
+            callback();
+        }
+    }
+}
+
+/// Controls what a send() on a full bounded channel does before it blocks.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SendBackoff {
+    /// Park the sending thread right away until there is room in the channel.
+    #[default]
+    Park,
+    /// Retry up to `spins` times with an exponential spin backoff before parking.
+    /// This reduces latency for bounded channels that are drained quickly.
+    SpinThenPark { spins: u32 },
+}
+
+/// What receive() does while there are no channels, see DynMultiReceiver::with_empty_behavior().
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum EmptyBehavior {
+    /// Wait until a channel is created and has a message.
+    #[default]
+    Block,
+    /// Panic instead of waiting, which turns an accidental permanent hang into a loud failure.
+    Panic,
+    /// Return None from receive_or_none() instead of waiting. receive() can't return nothing, so it panics.
+    ReturnNone,
+}
+
+/// How receive() picks between the channels of the same priority that have a message.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Policy {
+    /// Sample a channel at random, with a probability proportional to its weight.
+    #[default]
+    Weighted,
+    /// Serve the channels in a fixed repeating pattern that matches the weight proportions exactly,
+    /// e.g. channels with the weights 1 and 3 are served once and three times in every four receives.
+    /// The pattern interleaves the channels as evenly as possible.
+    DeterministicWeighted,
+    /// Serve the channels one after another in the order of their ids, ignoring their weights.
+    /// Channels without a message are skipped. Ids follow the creation order until they wrap around
+    /// after u32::MAX, see DynSender::id().
+    ///
+    /// The position in the rotation is shared by all clones of the DynMultiReceiver, and every receive claims
+    /// its turn atomically. So concurrent consumers together serve the channels evenly, instead of each
+    /// consumer sampling on its own like with Policy::Weighted.
+    RoundRobin,
+}
+
+/// The sending side of a channel, created by DynMultiReceiver::new_channel() and friends.
+///
+/// Cloning the sender lets several producers send into the same channel. All clones share the id
+/// of the channel, so removing, freezing or moving the channel through any of them affects all of them.
+/// Dropping the last clone closes the channel: it is removed once its buffered messages are delivered.
+pub struct DynSender<T, P: Priority, S: BuildHasher = ahash::RandomState> {
+    id: u32,
+    count_multireceivers: Arc<AtomicUsize>,
+    cleanup: Cleanup<S>,
+    condvar: Arc<(Mutex<Wakes>, Condvar)>,
+    state: Arc<RwLock<DynState<T, P, S>>>,
+    sequence: Arc<AtomicU64>,
+    backoff: SendBackoff,
+    backlog: Arc<Backlog>,
+    ready: Arc<ReadyBit>,
+    activity: Arc<Activity>,
+    reserve: Option<Arc<ReserveGate>>,
+    limit: Option<Arc<SendLimit>>,
+    handover: Option<Arc<AtomicUsize>>,
+    senders: Arc<AtomicUsize>,
+    closed: Arc<AtomicBool>,
+    inner: crossbeam_channel::Sender<Envelope<T>>,
+}
+
+impl<T, P: Priority, S: BuildHasher> Clone for DynSender<T, P, S> {
+    fn clone(&self) -> Self {
+        self.senders.fetch_add(1, Ordering::SeqCst);
+        Self {
+            id: self.id,
+            count_multireceivers: self.count_multireceivers.clone(),
+            cleanup: self.cleanup.clone(),
+            condvar: self.condvar.clone(),
+            state: self.state.clone(),
+            sequence: self.sequence.clone(),
+            backoff: self.backoff,
+            backlog: self.backlog.clone(),
+            ready: self.ready.clone(),
+            activity: self.activity.clone(),
+            reserve: self.reserve.clone(),
+            limit: self.limit.clone(),
+            handover: self.handover.clone(),
+            senders: self.senders.clone(),
+            closed: self.closed.clone(),
+            inner: self.inner.clone(),
+        }
+    }
+}
+
+impl<T, P: Priority, S: BuildHasher> Drop for DynSender<T, P, S> {
+    fn drop(&mut self) {
+        if self.senders.fetch_sub(1, Ordering::SeqCst) > 1 || self.closed.load(Ordering::SeqCst) {
+            return;
+        }
+        self.on_disconnected();
+    }
+}
+
+impl<T, P: Priority, S: BuildHasher> DynSender<T, P, S> {
+    /// Returns the channel of this sender, or None if it was removed, even if a newer channel reused its id.
+    fn own_receiver<'a>(&self, state: &'a DynState<T, P, S>) -> Option<&'a DynReceiver<T>> {
+        state
+            .receiver(self.id)
+            .filter(|r| Arc::ptr_eq(&r.senders, &self.senders))
+    }
+
+    /// Removes the channel once its last sender is dropped or it is closed, after its buffered messages are delivered.
+    fn on_disconnected(&self) {
+        // The channel is removed once its buffered messages are delivered
+        if !self.inner.is_empty() {
+            return;
+        }
+        // Remove it right away, so an idle multichannel doesn't keep stale channels around.
+        // try_write(), as the sender may be dropped while the lock is held, e.g. in a receive_matching() predicate.
+        if let Ok(mut state) = self.state.try_write() {
+            // Once the channel was removed, its id may have been reused by a newer channel, which must be left alone
+            let Some(empty) = self.own_receiver(&state).map(|r| r.is_empty()) else {
+                return;
+            };
+            if empty {
+                let removed = state.try_remove_receiver(self.id);
+                let no_channels = state.is_empty();
+                drop(state);
+                drop(removed);
+                if no_channels {
+                    // Let a receiver waiting in recv() or iter() return
+                    wake_all(&self.condvar, 1);
+                }
+                return;
+            }
+        }
+        self.cleanup.1.lock().unwrap().insert(self.id);
+        self.cleanup.0.store(true, Ordering::Relaxed);
+        // Let a waiting receiver clean up, which may leave no channels
+        wake_all(&self.condvar, 1);
+    }
+
+    /// Returns the id of the channel, which is unique among the live channels of the multichannel.
+    ///
+    /// Ids are handed out in increasing order. Only once u32::MAX was handed out, the ids of removed channels
+    /// are reused, so a sender kept around after its channel was removed may then refer to a newer channel.
+    pub fn id(&self) -> u32 {
+        self.id
+    }
+
+    /// Returns the amount of messages buffered in this channel, including while it is frozen.
+    /// For a zero capacity channel this is always 0, as messages are handed over directly.
+    ///
+    /// A message receive_oldest() already looked at, but didn't take yet, is not counted.
+    pub fn len(&self) -> usize {
+        self.inner.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.inner.is_empty()
+    }
+
+    /// Returns the amount of live clones of this sender, including this one.
+    ///
+    /// Once the last one is dropped, the channel is closed and removed after its buffered messages are delivered.
+    pub fn sender_count(&self) -> usize {
+        self.senders.load(Ordering::SeqCst)
+    }
+
+    /// Close the channel for all clones of this sender: further sends fail with SendError::Disconnected,
+    /// but the buffered messages are still delivered in the usual order. Once they are, the channel is removed.
+    /// This differs from DynMultiReceiver::remove_channel(), which drops the buffered messages right away.
+    ///
+    /// A send() that is already blocked on a full channel may still deliver its message.
+    pub fn close(&self) {
+        if self.closed.swap(true, Ordering::SeqCst) {
+            return;
+        }
+        if let Some(gate) = &self.reserve {
+            *gate.lock.lock().unwrap() = true;
+            gate.condvar.notify_all();
+        }
+        self.on_disconnected();
+    }
+
+    /// Returns the capacity of the channel, or None if it is unbounded.
+    /// For a channel created with new_channel_with_reserve(), this includes the reserve.
+    pub fn capacity(&self) -> Option<usize> {
+        self.inner.capacity()
+    }
+
+    /// Returns true if a send() would block because the channel is full. Unbounded channels are never full.
+    ///
+    /// A zero capacity channel only hands a message over while the sender waits for a receiver to take it,
+    /// so it counts as full unless a receiver of the multichannel is currently waiting for a message.
+    /// That receiver may still serve a higher priority channel first. For a channel created with
+    /// new_channel_with_reserve(), the reserve doesn't count, as send() can't use it.
+    pub fn is_full(&self) -> bool {
+        match &self.reserve {
+            Some(gate) => self.inner.len() >= gate.main,
+            None if self.handover.is_some() => !self.condvar.0.lock().unwrap().has_waiters(),
+            None => self.inner.is_full(),
+        }
+    }
+
+    fn wake_receiver(&self) {
+        self.ready.set();
+        let (lock, condvar) = &*self.condvar;
+        let (selective, tasks) = {
+            let mut wakes = lock.lock().unwrap();
+            wakes.pending += 1;
+            wakes.bump();
+            (wakes.selective > 0, std::mem::take(&mut wakes.tasks))
+        };
+        tasks.wake();
+        if selective {
+            condvar.notify_all();
+        } else {
+            condvar.notify_one();
+        }
+    }
+
+    /// Send a message into the channel.
+    ///
+    /// For bounded channels this blocks while the channel is full. If the channel was created
+    /// with new_channel_with_reserve(), this already blocks once the main capacity is used up,
+    /// leaving the reserve to send_urgent().
+    pub fn send(&self, value: T) -> Result<(), SendError> {
+        let counted = self.check_send()?;
+        let sent = match &self.reserve {
+            Some(gate) => self
+                .send_gated(gate, value, None)
+                .map_err(|_| SendError::Disconnected),
+            None => self.send_inner(value),
+        };
+        match sent {
+            Ok(()) => self.on_sent(counted),
+            Err(_) => self.release_send(),
+        }
+        sent
+    }
+
+    /// Send all values in order, like send() for each of them, but wake receivers once for the whole batch.
+    ///
+    /// If the channel fills up, the values sent so far are announced before blocking, so receivers can make room.
+    /// Stops at the first value that can't be sent and returns the error, the remaining values are dropped.
+    /// Zero capacity channels and channels created with new_channel_with_reserve() send and wake per value.
+    pub fn send_all<I: IntoIterator<Item = T>>(&self, values: I) -> Result<(), SendError> {
+        if self.handover.is_some() || self.reserve.is_some() {
+            return values.into_iter().try_for_each(|value| self.send(value));
+        }
+        let announce = |amount: usize| {
+            if amount > 0 {
+                self.ready.set();
+                wake_all(&self.condvar, amount);
+            }
+        };
+        let mut unannounced = 0;
+        let sent = values.into_iter().try_for_each(|value| {
+            let counted = self.check_send()?;
+            let envelope = Envelope {
+                seq: self.sequence.fetch_add(1, Ordering::Relaxed),
+                value,
+            };
+            let sent = match self.inner.try_send(envelope) {
+                Ok(()) => {
+                    unannounced += 1;
+                    Ok(())
+                }
+                Err(crossbeam_channel::TrySendError::Full(envelope)) => {
+                    announce(std::mem::take(&mut unannounced));
+                    self.send_inner(envelope.value)
+                }
+                Err(crossbeam_channel::TrySendError::Disconnected(_)) => {
+                    Err(SendError::Disconnected)
+                }
+            };
+            match sent {
+                Ok(()) => self.on_sent(counted),
+                Err(_) => self.release_send(),
+            }
+            sent
+        });
+        announce(unannounced);
+        sent
+    }
+
+    /// Send a message that may use the reserve capacity of a channel created with
+    /// new_channel_with_reserve(). For all other channels this behaves exactly like send().
+    pub fn send_urgent(&self, value: T) -> Result<(), SendError> {
+        let counted = self.check_send()?;
+        let sent = self.send_inner(value);
+        match sent {
+            Ok(()) => self.on_sent(counted),
+            Err(_) => self.release_send(),
+        }
+        sent
+    }
+
+    /// Like send(), but returns the message with TrySendError::Full instead of blocking if the channel is full.
+    ///
+    /// If the channel was created with new_channel_with_reserve(), it counts as full once the main capacity
+    /// is used up. Zero capacity channels only hand a message over while the sender waits for a receiver,
+    /// so try_send() always returns TrySendError::Full for them.
+    pub fn try_send(&self, value: T) -> Result<(), TrySendError<T>> {
+        let counted = match self.check_send() {
+            Ok(counted) => counted,
+            Err(error) => return Err(TrySendError::new(error, value)),
+        };
+        let sent = match &self.reserve {
+            Some(gate) => {
+                let closed = gate.lock.lock().unwrap();
+                if *closed {
+                    Err(TrySendError::Disconnected(value))
+                } else if self.inner.len() >= gate.main {
+                    Err(TrySendError::Full(value))
+                } else {
+                    self.try_send_inner(value)
+                }
+            }
+            None => self.try_send_inner(value),
+        };
+        match &sent {
+            Ok(()) => self.on_sent(counted),
+            Err(_) => self.release_send(),
+        }
+        sent
+    }
+
+    fn try_send_inner(&self, value: T) -> Result<(), TrySendError<T>> {
+        let envelope = Envelope {
+            seq: self.sequence.fetch_add(1, Ordering::Relaxed),
+            value,
+        };
+        match self.inner.try_send(envelope) {
+            Ok(()) => {
+                self.wake_receiver();
+                Ok(())
+            }
+            Err(crossbeam_channel::TrySendError::Full(envelope)) => {
+                Err(TrySendError::Full(envelope.value))
+            }
+            Err(crossbeam_channel::TrySendError::Disconnected(envelope)) => {
+                Err(TrySendError::Disconnected(envelope.value))
+            }
+        }
+    }
+
+    /// Checks that a message may be sent and counts it in the backlog.
+    /// Returns the backlog before the message, to be passed to on_sent() once it was sent.
+    fn check_send(&self) -> Result<isize, SendError> {
+        if self.count_multireceivers.load(Ordering::Relaxed) == 0
+            || self.closed.load(Ordering::SeqCst)
+        {
+            return Err(SendError::Disconnected);
+        }
+        if let Some(limit) = &self.limit {
+            if !limit.reserve() {
+                return Err(SendError::Exhausted);
+            }
+        }
+        // Counted before the message becomes visible, so a receive can't be accounted before its send
+        Ok(self.backlog.count())
+    }
+
+    /// Gives back the send reserved by check_send() for a message that wasn't sent.
+    fn release_send(&self) {
+        self.backlog.sub(1);
+        if let Some(limit) = &self.limit {
+            limit.release();
+        }
+    }
+
+    fn on_sent(&self, backlog_before: isize) {
+        self.backlog.committed(backlog_before);
+        self.activity.touch();
+        self.activity.sent.fetch_add(1, Ordering::Relaxed);
+        if let Some(limit) = &self.limit {
+            limit.sent.fetch_add(1, Ordering::SeqCst);
+            // The receiver may have taken the last message before it was counted
+            if limit.exhausted() && self.inner.is_empty() {
+                self.cleanup.1.lock().unwrap().insert(self.id);
+                self.cleanup.0.store(true, Ordering::Relaxed);
+            }
+        }
+    }
+
+    /// Like send(), but gives up once `timeout` has passed while the channel is full,
+    /// returning the message with SendTimeoutError::Timeout.
+    ///
+    /// If the channel was created with new_channel_with_reserve(), this waits for room in the main capacity,
+    /// like send(). For zero capacity channels the timeout bounds the wait for a receiver to take the message.
+    pub fn send_timeout(&self, value: T, timeout: Duration) -> Result<(), SendTimeoutError<T>> {
+        let deadline = Instant::now().checked_add(timeout);
+        let counted = match self.check_send() {
+            Ok(counted) => counted,
+            Err(error) => return Err(SendTimeoutError::new(error, value)),
+        };
+        let sent = match &self.reserve {
+            Some(gate) => self.send_gated(gate, value, deadline),
+            None => self.send_inner_until(value, deadline),
+        };
+        match &sent {
+            Ok(()) => self.on_sent(counted),
+            Err(_) => self.release_send(),
+        }
+        sent
+    }
+
+    /// Sends into a channel with a reserve, waiting until the deadline, if any, for room in the main capacity.
+    ///
+    /// The gate is held while checking and sending, so concurrent normal sends can't slip past main together.
+    /// It is never held while blocking on the channel though, as closing the channel and dropping its receiver
+    /// need the gate as well. If urgent sends used up the reserve in the meantime, this waits for the next receive.
+    fn send_gated(
+        &self,
+        gate: &ReserveGate,
+        mut value: T,
+        deadline: Option<Instant>,
+    ) -> Result<(), SendTimeoutError<T>> {
+        let mut closed = gate.lock.lock().unwrap();
+        loop {
+            if *closed {
+                return Err(SendTimeoutError::Disconnected(value));
+            }
+            if self.inner.len() < gate.main {
+                value = match self.try_send_inner(value) {
+                    Ok(()) => return Ok(()),
+                    Err(TrySendError::Full(value)) => value,
+                    Err(error) => return Err(SendTimeoutError::Disconnected(error.into_inner())),
+                };
+            }
+            closed = match deadline {
+                Some(deadline) => {
+                    let Some(left) = deadline.checked_duration_since(Instant::now()) else {
+                        return Err(SendTimeoutError::Timeout(value));
+                    };
+                    gate.condvar.wait_timeout(closed, left).unwrap().0
+                }
+                None => gate.condvar.wait(closed).unwrap(),
+            };
+        }
+    }
+
+    fn send_inner(&self, value: T) -> Result<(), SendError> {
+        self.send_inner_until(value, None)
+            .map_err(|_| SendError::Disconnected)
+    }
+
+    /// Sends the message, blocking while the channel is full until the deadline, if any.
+    fn send_inner_until(
+        &self,
+        value: T,
+        deadline: Option<Instant>,
+    ) -> Result<(), SendTimeoutError<T>> {
+        if let Some(handover) = &self.handover {
+            // Announced before the wake, so receivers consider the channel until the message is handed over
+            handover.fetch_add(1, Ordering::SeqCst);
+            self.wake_receiver();
+        }
+        let mut envelope = Envelope {
+            seq: self.sequence.fetch_add(1, Ordering::Relaxed),
+            value,
+        };
+        if let SendBackoff::SpinThenPark { spins } = self.backoff {
+            if self.inner.capacity().is_some_and(|cap| cap > 0) {
+                let backoff = Backoff::new();
+                for _ in 0..spins {
+                    match self.inner.try_send(envelope) {
+                        Ok(()) => {
+                            self.wake_receiver();
+                            return Ok(());
+                        }
+                        Err(crossbeam_channel::TrySendError::Full(rejected)) => envelope = rejected,
+                        Err(crossbeam_channel::TrySendError::Disconnected(rejected)) => {
+                            return Err(SendTimeoutError::Disconnected(rejected.value))
+                        }
+                    }
+                    backoff.spin();
+                }
+            }
+        }
+        let sent = match deadline {
+            Some(deadline) => self.inner.send_deadline(envelope, deadline),
+            None => self.inner.send(envelope).map_err(|rejected| {
+                crossbeam_channel::SendTimeoutError::Disconnected(rejected.into_inner())
+            }),
+        };
+        match &self.handover {
+            Some(handover) => {
+                handover.fetch_sub(1, Ordering::SeqCst);
+            }
+            None if sent.is_ok() => self.wake_receiver(),
+            None => {}
+        }
+        sent.map_err(|error| match error {
+            crossbeam_channel::SendTimeoutError::Timeout(rejected) => {
+                SendTimeoutError::Timeout(rejected.value)
+            }
+            crossbeam_channel::SendTimeoutError::Disconnected(rejected) => {
+                SendTimeoutError::Disconnected(rejected.value)
+            }
+        })
+    }
+
+    /// Move the channel to another priority, e.g. to promote it. Its buffered messages, weight and
+    /// frozen state are kept, so they are delivered at the new priority from the next receive() on.
+    /// The frozen default of the new tier (see with_tier_defaults()) is not applied.
+    /// Does nothing if the channel was already removed.
+    pub fn set_priority(&self, priority: P) {
+        let mut state = self.state.write().unwrap();
+        if self.own_receiver(&state).is_some() {
+            state.move_receiver(self.id, priority);
+        }
+    }
+
+    /// Change the weight of the channel, which is used from the next receive() on.
+    /// The weight must be greater than 0. Does nothing if the channel was already removed.
+    pub fn set_weight(&self, weight: u32) {
+        assert!(weight > 0, "Weight must be greater than 0");
+        let mut state = self.state.write().unwrap();
+        if self.own_receiver(&state).is_none() {
+            return;
+        }
+        if let Some((group_idx, inner_idx)) = state.locate(self.id) {
+            let group = &mut state.groups[group_idx];
+            group.receivers[inner_idx].weight = weight;
+            group.weights.take();
+        }
+    }
+
+    /// Returns true if the channel is frozen, and false if it isn't or was already removed.
+    pub fn is_frozen(&self) -> bool {
+        let state = self.state.read().unwrap();
+        self.own_receiver(&state).is_some_and(|r| r.is_frozen())
+    }
+
+    /// Freeze or unfreeze the channel. Unfreezing wakes up receivers waiting for a message,
+    /// so they can deliver the messages that were buffered while the channel was frozen.
+    /// Does nothing if the channel was already removed.
+    pub fn set_frozen(&self, frozen: bool) {
+        let thawed = {
+            let state = self.state.read().unwrap();
+            state.check_freezing(frozen);
+            self.own_receiver(&state)
+                .and_then(|_| state.try_set_frozen(self.id, frozen))
+        };
+        wake_all(&self.condvar, thawed.unwrap_or(0));
+    }
+}
+
+/// The wake accounting shared by all senders and receivers, protected by the condvar mutex.
+#[derive(Default)]
+struct Wakes {
+    // Wake tokens, roughly the amount of deliverable messages no receiver has taken yet
+    pending: usize,
+    // Receivers blocked in wait_for_wake_until(), for DynSender::is_full()
+    waiting: usize,
+    // Increased on every wake, so selective receivers can tell that something changed
+    epoch: u64,
+    // Receivers waiting in a filtered receive. They don't take tokens, so they need every wake.
+    selective: usize,
+    // Tasks waiting in receive_async(), which need every wake as well
+    tasks: Tasks,
+    // Handed out by recv_ready_signal(), fired on every wake
+    signals: Vec<crossbeam_channel::Sender<()>>,
+}
+
+impl Wakes {
+    /// Returns true if a receiver is blocked until the next message, including filtered and async receives.
+    fn has_waiters(&self) -> bool {
+        self.waiting > 0 || self.selective > 0 || !self.tasks.is_empty()
+    }
+
+    /// Increases the epoch and fires the readiness signals, dropping those whose receiver is gone.
+    fn bump(&mut self) {
+        self.epoch += 1;
+        self.signals.retain(|signal| {
+            !matches!(
+                signal.try_send(()),
+                Err(crossbeam_channel::TrySendError::Disconnected(()))
+            )
+        });
+    }
+}
+
+/// Wakers of tasks waiting in receive_async(). Always empty without the async feature.
+#[derive(Default)]
+struct Tasks(#[cfg(feature = "async")] Vec<std::task::Waker>);
+
+impl Tasks {
+    #[cfg(feature = "async")]
+    fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    #[cfg(not(feature = "async"))]
+    fn is_empty(&self) -> bool {
+        true
+    }
+
+    /// Must be called after releasing the condvar mutex, as a waker may poll right away.
+    fn wake(self) {
+        #[cfg(feature = "async")]
+        self.0.into_iter().for_each(std::task::Waker::wake);
+    }
+}
+
+/// Adds wake tokens for `amount` messages that became deliverable and wakes up all waiting receivers,
+/// so they re-scan the channels.
+fn wake_all(condvar: &(Mutex<Wakes>, Condvar), amount: usize) {
+    if amount == 0 {
+        return;
+    }
+    let (lock, condvar) = condvar;
+    let tasks = {
+        let mut wakes = lock.lock().unwrap();
+        wakes.pending += amount;
+        wakes.bump();
+        std::mem::take(&mut wakes.tasks)
+    };
+    tasks.wake();
+    condvar.notify_all();
+}
+
+/// The time of the last send or receive of a channel, used to reap idle channels,
+/// and the amount of sends and receives for stats().
+struct Activity {
+    created: Instant,
+    last: AtomicU64, // nanoseconds since created
+    sent: AtomicU64,
+    received: AtomicU64,
+}
+
+impl Activity {
+    fn new() -> Self {
+        Self {
+            created: Instant::now(),
+            last: AtomicU64::new(0),
+            sent: AtomicU64::new(0),
+            received: AtomicU64::new(0),
+        }
+    }
+
+    fn touch(&self) {
+        let nanos = self.created.elapsed().as_nanos() as u64;
+        self.last.fetch_max(nanos, Ordering::Relaxed);
+    }
+
+    fn idle_for(&self) -> Duration {
+        self.created.elapsed() - Duration::from_nanos(self.last.load(Ordering::Relaxed))
+    }
+}
+
+/// A message stamped with a global send sequence number.
+struct Envelope<T> {
+    seq: u64,
+    value: T,
+}
+
+/// Shared by a channel and its RawReceivers, so the messages left in the buffer are taken out of the
+/// backlog once the last of them is dropped, instead of when the channel is removed.
+struct ChannelBuffer<T> {
+    inner: crossbeam_channel::Receiver<Envelope<T>>,
+    backlog: Arc<Backlog>,
+}
+
+impl<T> Drop for ChannelBuffer<T> {
+    fn drop(&mut self) {
+        self.backlog.sub(self.inner.len());
+    }
+}
+
+struct DynReceiver<T> {
+    id: u32,
+    slot: usize,
+    ready: Arc<ReadyBit>,
+    weight: u32,
+    // Atomic, so freezing only needs the read lock of the state
+    frozen: AtomicBool,
+    reserve: Option<Arc<ReserveGate>>,
+    limit: Option<Arc<SendLimit>>,
+    backlog: Arc<Backlog>,
+    // Holds the head of the channel once it was peeked
+    front: Mutex<Option<Envelope<T>>>,
+    has_front: AtomicBool,
+    // Bytes served relative to the weight, used for size fairness
+    served: AtomicU64,
+    // Credit for Policy::DeterministicWeighted
+    deficit: AtomicI64,
+    passed_over: AtomicU64,
+    activity: Arc<Activity>,
+    // Senders waiting to hand over a message, only for zero capacity channels
+    handover: Option<Arc<AtomicUsize>>,
+    // Live clones of the sender, the channel is closed once all of them are dropped
+    senders: Arc<AtomicUsize>,
+    // Set by DynSender::close()
+    closed: Arc<AtomicBool>,
+    name: Option<String>,
+    inner: crossbeam_channel::Receiver<Envelope<T>>,
+    buffer: Arc<ChannelBuffer<T>>,
+}
+
+impl<T> DynReceiver<T> {
+    fn new(
+        id: u32,
+        weight: u32,
+        frozen: bool,
+        reserve: Option<Arc<ReserveGate>>,
+        limit: Option<Arc<SendLimit>>,
+        backlog: Arc<Backlog>,
+        inner: crossbeam_channel::Receiver<Envelope<T>>,
+    ) -> Self {
+        Self {
+            id,
+            // Assigned once the receiver is added to a group
+            slot: 0,
+            ready: Arc::new(ReadyBit::new()),
+            weight,
+            frozen: AtomicBool::new(frozen),
+            reserve,
+            limit,
+            buffer: Arc::new(ChannelBuffer {
+                inner: inner.clone(),
+                backlog: backlog.clone(),
+            }),
+            backlog,
+            front: Mutex::new(None),
+            has_front: AtomicBool::new(false),
+            served: AtomicU64::new(0),
+            deficit: AtomicI64::new(0),
+            passed_over: AtomicU64::new(0),
+            activity: Arc::new(Activity::new()),
+            handover: (inner.capacity() == Some(0)).then(|| Arc::new(AtomicUsize::new(0))),
+            senders: Arc::new(AtomicUsize::new(1)),
+            closed: Arc::new(AtomicBool::new(false)),
+            name: None,
+            inner,
+        }
+    }
+
+    fn is_frozen(&self) -> bool {
+        self.frozen.load(Ordering::SeqCst)
+    }
+
+    fn is_empty(&self) -> bool {
+        !self.has_front.load(Ordering::Relaxed) && self.inner.is_empty()
+    }
+
+    fn len(&self) -> usize {
+        self.inner.len() + self.has_front.load(Ordering::Relaxed) as usize
+    }
+
+    /// Returns true if the channel holds a message, or a sender is waiting to hand one over
+    /// to a zero capacity channel.
+    fn has_message(&self) -> bool {
+        match &self.handover {
+            // A peeked message was already handed over
+            Some(handover) => {
+                self.has_front.load(Ordering::Relaxed) || handover.load(Ordering::SeqCst) > 0
+            }
+            None => !self.is_empty(),
+        }
+    }
+
+    /// Returns true if the channel won't ever deliver another message,
+    /// because all clones of its sender were dropped or it is a limited channel that used up its sends.
+    fn is_finished(&self) -> bool {
+        (self.senders.load(Ordering::SeqCst) == 0
+            || self.closed.load(Ordering::SeqCst)
+            || self.limit.as_ref().is_some_and(|limit| limit.exhausted()))
+            && self.is_empty()
+    }
+
+    /// The predicates of receive_matching() run under this lock. A panicking one leaves the front
+    /// untouched, so a poisoned lock is recovered from instead of failing every later receive.
+    fn lock_front(&self) -> MutexGuard<'_, Option<Envelope<T>>> {
+        recover(self.front.lock())
+    }
+
+    fn try_recv(&self) -> Result<T, crossbeam_channel::TryRecvError> {
+        let envelope = {
+            let mut front = self.lock_front();
+            match front.take() {
+                Some(envelope) => {
+                    self.has_front.store(false, Ordering::Relaxed);
+                    envelope
+                }
+                None => self.inner.try_recv()?,
+            }
+        };
+        self.on_received();
+        Ok(envelope.value)
+    }
+
+    /// Returns the sequence number of the head of the channel without consuming it.
+    fn peek_seq(&self) -> Result<u64, crossbeam_channel::TryRecvError> {
+        let mut front = self.lock_front();
+        if let Some(envelope) = &*front {
+            return Ok(envelope.seq);
+        }
+        let envelope = self.inner.try_recv()?;
+        let seq = envelope.seq;
+        *front = Some(envelope);
+        self.has_front.store(true, Ordering::Relaxed);
+        Ok(seq)
+    }
+
+    /// Takes the peeked head of the channel, if it is still the message with the given sequence number.
+    fn take_front(&self, seq: u64) -> Option<T> {
+        let envelope = {
+            let mut front = self.lock_front();
+            if front.as_ref()?.seq != seq {
+                return None;
+            }
+            self.has_front.store(false, Ordering::Relaxed);
+            front.take()?
+        };
+        self.on_received();
+        Some(envelope.value)
+    }
+
+    /// Returns true if the head of the channel matches `pred`, peeking it without consuming it.
+    /// Only called for channels with a message.
+    fn peek_matches(&self, pred: impl Fn(&T) -> bool) -> bool {
+        let mut front = self.lock_front();
+        if front.is_none() {
+            let Ok(envelope) = self.inner.try_recv() else {
+                // A message about to be handed over can't be peeked yet, so it may match
+                return self.handover.is_some();
+            };
+            *front = Some(envelope);
+            self.has_front.store(true, Ordering::Relaxed);
+        }
+        front.as_ref().is_some_and(|envelope| pred(&envelope.value))
+    }
+
+    /// Takes the peeked head of the channel, if it matches `pred`.
+    fn take_front_if(
+        &self,
+        pred: impl Fn(&T) -> bool,
+    ) -> Result<T, crossbeam_channel::TryRecvError> {
+        let envelope = {
+            let mut front = self.lock_front();
+            if !front.as_ref().is_some_and(|envelope| pred(&envelope.value)) {
+                return Err(crossbeam_channel::TryRecvError::Empty);
+            }
+            self.has_front.store(false, Ordering::Relaxed);
+            front.take().unwrap()
+        };
+        self.on_received();
+        Ok(envelope.value)
+    }
+
+    /// Must be called after a message was taken out of the channel.
+    fn on_received(&self) {
+        self.activity.touch();
+        self.activity.received.fetch_add(1, Ordering::Relaxed);
+        if let Some(gate) = &self.reserve {
+            // Only taken to not miss a waiting sender, the flag itself isn't touched
+            let _guard = recover(gate.lock.lock());
+            gate.condvar.notify_all();
+        }
+    }
+}
+
+impl<T> Drop for DynReceiver<T> {
+    fn drop(&mut self) {
+        // The peeked message is dropped right away, the rest of the buffer once no RawReceiver holds it
+        self.backlog
+            .sub(self.has_front.load(Ordering::Relaxed) as usize);
+        if let Some(gate) = &self.reserve {
+            *gate.lock.lock().unwrap() = true;
+            gate.condvar.notify_all();
+        }
+    }
+}
+
+/// A message taken out of a channel by the selection logic of receive().
+struct Selected<'a, T> {
+    receiver: &'a DynReceiver<T>,
+    value: T,
+}
+
+/// The outcome of a full pass of the selection logic over all groups.
+enum Selection<'a, T> {
+    Found(Selected<'a, T>),
+    /// Nothing was deliverable.
+    Empty,
+    /// Nothing was deliverable, but a sender of a zero capacity channel is about to hand over a message.
+    Rendezvous,
+}
+
+/// The send limit of a channel created with new_limited_channel().
+struct SendLimit {
+    max: u64,
+    reserved: AtomicU64,
+    sent: AtomicU64,
+}
+
+impl SendLimit {
+    /// Reserves one of the remaining sends, returning false if there are none left.
+    fn reserve(&self) -> bool {
+        self.reserved
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |reserved| {
+                (reserved < self.max).then_some(reserved + 1)
+            })
+            .is_ok()
+    }
+
+    fn release(&self) {
+        self.reserved.fetch_sub(1, Ordering::Relaxed);
+    }
+
+    fn exhausted(&self) -> bool {
+        self.sent.load(Ordering::SeqCst) >= self.max
+    }
+}
+
+/// The messages a consumer received with receive_tracked() and didn't acknowledge yet.
+struct InFlight {
+    limit: usize,
+    count: Mutex<usize>,
+    condvar: Condvar,
+}
+
+impl InFlight {
+    fn new(limit: usize) -> Self {
+        Self {
+            limit,
+            count: Mutex::new(0),
+            condvar: Condvar::new(),
+        }
+    }
+}
+
+/// The RNG a consumer samples the weighted selection from.
+enum ConsumerRng<'a> {
+    Thread(ThreadRng),
+    // Locked per sample, so that no lock is held while waiting for a message
+    Seeded(&'a Mutex<SmallRng>),
+}
+
+impl RngCore for ConsumerRng<'_> {
+    fn next_u32(&mut self) -> u32 {
+        match self {
+            Self::Thread(rng) => rng.next_u32(),
+            Self::Seeded(rng) => recover(rng.lock()).next_u32(),
+        }
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        match self {
+            Self::Thread(rng) => rng.next_u64(),
+            Self::Seeded(rng) => recover(rng.lock()).next_u64(),
+        }
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        match self {
+            Self::Thread(rng) => rng.fill_bytes(dest),
+            Self::Seeded(rng) => recover(rng.lock()).fill_bytes(dest),
+        }
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), rand::Error> {
+        match self {
+            Self::Thread(rng) => rng.try_fill_bytes(dest),
+            Self::Seeded(rng) => recover(rng.lock()).try_fill_bytes(dest),
+        }
+    }
+}
+
+/// Acknowledges a message received with DynMultiReceiver::receive_tracked().
+///
+/// Dropping the Ack acknowledges the message as well.
+pub struct Ack {
+    in_flight: Arc<InFlight>,
+}
+
+impl Ack {
+    /// Acknowledge the message, freeing its in-flight slot of the consumer.
+    pub fn ack(self) {}
+}
+
+impl Drop for Ack {
+    fn drop(&mut self) {
+        *self.in_flight.count.lock().unwrap() -= 1;
+        self.in_flight.condvar.notify_one();
+    }
+}
+
+/// Reusable buffers for the candidates considered by receive_with_scratch().
+///
+/// Every consumer keeps its own buffers for receive(), but threads sharing a consumer, e.g. through an Arc,
+/// allocate fresh ones while another thread uses them. Giving each thread its own scratch avoids that,
+/// so receive_with_scratch() doesn't allocate once the buffers grew large enough.
+#[derive(Debug, Default)]
+pub struct ReceiveScratch {
+    candidates: SmallVec<[(usize, usize); 8]>, // (group_idx, inner_idx)
+    ordered: SmallVec<[(u64, (usize, usize)); 8]>, // (sort key, candidate)
+}
+
+impl ReceiveScratch {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+/// Direct access to the buffer of a single channel, created by DynMultiReceiver::raw_receiver().
+///
+/// **This bypasses the priority scheduler.** Messages taken here are never seen by receive(), no matter
+/// their priority, and a consumer pulling from here as fast as messages arrive starves every other channel
+/// of the multichannel. Frozen state, weights and policies don't apply, and a message already peeked by
+/// the multichannel, e.g. by receive_oldest(), is only delivered by the multichannel.
+///
+/// The buffer stays alive while a RawReceiver exists: once the channel is removed from the multichannel,
+/// its buffered messages stay here and its senders keep sending into it, blocking once it's full,
+/// until the RawReceiver is dropped as well. Until then they still count towards the backlog watermarks.
+///
+/// The buffer holds the messages together with their send sequence number,
+/// so instead of the crossbeam_channel::Receiver itself, this offers the same receive methods
+/// and can be added to a crossbeam_channel::Select with register().
+pub struct RawReceiver<T> {
+    buffer: Arc<ChannelBuffer<T>>,
+    activity: Arc<Activity>,
+    reserve: Option<Arc<ReserveGate>>,
+    backlog: Arc<Backlog>,
+    total_received: Arc<AtomicU64>,
+}
+
+impl<T> RawReceiver<T> {
+    pub fn try_recv(&self) -> Result<T, crossbeam_channel::TryRecvError> {
+        self.buffer
+            .inner
+            .try_recv()
+            .map(|envelope| self.on_received(envelope))
+    }
+
+    pub fn recv(&self) -> Result<T, crossbeam_channel::RecvError> {
+        self.buffer
+            .inner
+            .recv()
+            .map(|envelope| self.on_received(envelope))
+    }
+
+    pub fn recv_timeout(
+        &self,
+        timeout: Duration,
+    ) -> Result<T, crossbeam_channel::RecvTimeoutError> {
+        self.buffer
+            .inner
+            .recv_timeout(timeout)
+            .map(|envelope| self.on_received(envelope))
+    }
+
+    /// Add a receive operation on this channel to `select` and return its index.
+    /// Once the operation is selected, complete it with complete().
+    pub fn register<'a>(&'a self, select: &mut crossbeam_channel::Select<'a>) -> usize {
+        select.recv(&self.buffer.inner)
+    }
+
+    /// Complete a receive operation selected in a crossbeam_channel::Select, see register().
+    pub fn complete(
+        &self,
+        operation: crossbeam_channel::SelectedOperation<'_>,
+    ) -> Result<T, crossbeam_channel::RecvError> {
+        operation
+            .recv(&self.buffer.inner)
+            .map(|envelope| self.on_received(envelope))
+    }
+
+    pub fn len(&self) -> usize {
+        self.buffer.inner.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.buffer.inner.is_empty()
+    }
+
+    /// Keeps the counters of the multichannel in step, like a delivery by receive() would.
+    fn on_received(&self, envelope: Envelope<T>) -> T {
+        self.activity.touch();
+        self.activity.received.fetch_add(1, Ordering::Relaxed);
+        if let Some(gate) = &self.reserve {
+            // Only taken to not miss a waiting sender, the flag itself isn't touched
+            let _guard = recover(gate.lock.lock());
+            gate.condvar.notify_all();
+        }
+        self.total_received.fetch_add(1, Ordering::Relaxed);
+        self.backlog.sub(1);
+        envelope.value
+    }
+}
+
+/// An iterator over the messages that are deliverable right now, created by DynMultiReceiver::try_iter().
+pub struct TryIter<'a, T, P: Priority, S: BuildHasher = ahash::RandomState> {
+    receiver: &'a DynMultiReceiver<T, P, S>,
+}
+
+impl<T, P: Priority, S: BuildHasher> Iterator for TryIter<'_, T, P, S> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<T> {
+        self.receiver.try_receive().ok()
+    }
+}
+
+/// A blocking iterator over the received messages, created by DynMultiReceiver::iter().
+pub struct Iter<'a, T, P: Priority, S: BuildHasher = ahash::RandomState> {
+    receiver: &'a DynMultiReceiver<T, P, S>,
+}
+
+impl<T, P: Priority, S: BuildHasher> Iterator for Iter<'_, T, P, S> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<T> {
+        self.receiver.recv_until_no_channels().unwrap()
+    }
+}
+
+/// The future of DynMultiReceiver::receive_async().
+#[cfg(feature = "async")]
+struct ReceiveFuture<'a, T, P: Priority, S: BuildHasher> {
+    receiver: &'a DynMultiReceiver<T, P, S>,
+}
+
+#[cfg(feature = "async")]
+impl<T, P: Priority, S: BuildHasher> std::future::Future for ReceiveFuture<'_, T, P, S> {
+    type Output = T;
+
+    fn poll(self: std::pin::Pin<&mut Self>, cx: &mut std::task::Context<'_>) -> std::task::Poll<T> {
+        self.receiver
+            .poll_receive(cx, false)
+            .map(|value| value.expect("receive_async() doesn't stop without channels"))
+    }
+}
+
+/// An async stream of received messages, created by DynMultiReceiver::into_stream().
+///
+/// It ends once there are no channels left. Implements futures_core::Stream, so the stream combinators
+/// of the futures crate work on it.
+#[cfg(feature = "async")]
+pub struct MultiStream<T, P: Priority, S: BuildHasher = ahash::RandomState> {
+    receiver: DynMultiReceiver<T, P, S>,
+}
+
+#[cfg(feature = "async")]
+impl<T, P: Priority, S: BuildHasher> futures_core::Stream for MultiStream<T, P, S> {
+    type Item = T;
+
+    /// Receives the next message, or returns Ready(None) once there are no channels left.
+    /// Registers the waker of the task to be woken by the next send otherwise.
+    fn poll_next(
+        self: std::pin::Pin<&mut Self>,
+        cx: &mut std::task::Context<'_>,
+    ) -> std::task::Poll<Option<T>> {
+        self.receiver.poll_receive(cx, true)
+    }
+}
+
+#[cfg(feature = "async")]
+impl<T, P: Priority, S: BuildHasher> MultiStream<T, P, S> {
+    /// Receives the next message, or returns None once there are no channels left.
+    pub async fn next(&mut self) -> Option<T> {
+        use futures_core::Stream;
+
+        std::future::poll_fn(|cx| std::pin::Pin::new(&mut *self).poll_next(cx)).await
+    }
+
+    pub fn into_inner(self) -> DynMultiReceiver<T, P, S> {
+        self.receiver
+    }
+}
+
+/// Blocks normal sends of a channel with a reserve once its main capacity is used up.
+struct ReserveGate {
+    main: usize,
+    lock: Mutex<bool>, // true once the receiver is gone or the channel was closed
+    condvar: Condvar,
+}
+
+pub struct DynMultiReceiver<T, P: Priority, S: BuildHasher = ahash::RandomState> {
+    amount_multireceivers: Arc<AtomicUsize>,
+    cleanup: Cleanup<S>,
+    condvar: Arc<(Mutex<Wakes>, Condvar)>,
+    // Notified whenever a channel is created, for wait_for_channel()
+    channel_added: Arc<(Mutex<()>, Condvar)>,
+    state: Arc<RwLock<DynState<T, P, S>>>,
+    sequence: Arc<AtomicU64>,
+    send_backoff: SendBackoff,
+    size_fn: Option<SizeFn<T>>,
+    policy: Policy,
+    track_starvation: bool,
+    empty_behavior: EmptyBehavior,
+    backlog: Arc<Backlog>,
+    total_received: Arc<AtomicU64>,
+    // Not shared with clones, every consumer has its own
+    in_flight: Arc<InFlight>,
+    rng: Option<Mutex<SmallRng>>,
+    // Candidate buffers reused across receives, so a steady state receive doesn't allocate
+    scratch: Mutex<ReceiveScratch>,
+}
+
+type SizeFn<T> = Arc<dyn Fn(&T) -> u64 + Send + Sync>;
+
+// Ids of the channels to remove, and whether there are any
+type Cleanup<S> = Arc<(AtomicBool, Mutex<std::collections::HashSet<u32, S>>)>;
+
+impl<T, P: Priority, S: BuildHasher> Clone for DynMultiReceiver<T, P, S> {
+    fn clone(&self) -> Self {
+        self.amount_multireceivers.fetch_add(1, Ordering::Relaxed);
+        Self {
+            amount_multireceivers: self.amount_multireceivers.clone(),
+            cleanup: self.cleanup.clone(),
+            condvar: self.condvar.clone(),
+            channel_added: self.channel_added.clone(),
+            state: self.state.clone(),
+            sequence: self.sequence.clone(),
+            send_backoff: self.send_backoff,
+            size_fn: self.size_fn.clone(),
+            policy: self.policy,
+            track_starvation: self.track_starvation,
+            empty_behavior: self.empty_behavior,
+            backlog: self.backlog.clone(),
+            total_received: self.total_received.clone(),
+            in_flight: Arc::new(InFlight::new(self.in_flight.limit)),
+            rng: self
+                .rng
+                .as_ref()
+                .map(|rng| Mutex::new(SmallRng::seed_from_u64(recover(rng.lock()).next_u64()))),
+            scratch: Mutex::new(ReceiveScratch::new()),
+        }
+    }
+}
+
+impl<T, P: Priority, S: BuildHasher> Drop for DynMultiReceiver<T, P, S> {
+    fn drop(&mut self) {
+        self.amount_multireceivers.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+/// Shows the channels per tier, without any messages, so T doesn't need to implement Debug.
+impl<T, P: Priority + std::fmt::Debug, S: BuildHasher> std::fmt::Debug
+    for DynMultiReceiver<T, P, S>
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        #[derive(Debug)]
+        #[allow(dead_code)] // Only read by Debug
+        struct Tier<'a, P> {
+            priority: &'a P,
+            channels: usize,
+            frozen: usize,
+        }
+        let state = self.state.read().unwrap();
+        let tiers: Vec<_> = state
+            .groups
+            .iter()
+            .map(|group| Tier {
+                priority: &group.priority,
+                channels: group.receivers.len(),
+                frozen: group.receivers.iter().filter(|r| r.is_frozen()).count(),
+            })
+            .collect();
+        f.debug_struct("DynMultiReceiver")
+            .field("groups", &state.groups.len())
+            .field("channels", &state.lookup.len())
+            .field("tiers", &tiers)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<T, P: Priority, S: BuildHasher + Clone + Default> Default for DynMultiReceiver<T, P, S> {
+    fn default() -> Self {
+        Self::with_hasher(S::default())
+    }
+}
+
+impl<T, P: Priority> DynMultiReceiver<T, P> {
+    pub fn new() -> Self {
+        Self::with_hasher(ahash::RandomState::new())
+    }
+}
+
+impl<T, P: Priority, S: BuildHasher> DynMultiReceiver<T, P, S> {
+    /// Like new(), but the channel ids are hashed with `hasher` instead of ahash,
+    /// e.g. with std::collections::hash_map::RandomState for a DoS resistant or a fixed hasher for reproducibility.
+    pub fn with_hasher(hasher: S) -> Self
+    where
+        S: Clone,
+    {
+        Self {
+            amount_multireceivers: Arc::new(AtomicUsize::new(1)),
+            cleanup: Arc::new((
+                AtomicBool::new(false),
+                Mutex::new(std::collections::HashSet::with_hasher(hasher.clone())),
+            )),
+            condvar: Arc::new((Mutex::new(Wakes::default()), Condvar::new())),
+            channel_added: Arc::new((Mutex::new(()), Condvar::new())),
+            state: Arc::new(RwLock::new(DynState::new(hasher))),
+            sequence: Arc::new(AtomicU64::new(0)),
+            send_backoff: SendBackoff::default(),
+            size_fn: None,
+            policy: Policy::default(),
+            track_starvation: false,
+            empty_behavior: EmptyBehavior::Block,
+            backlog: Arc::new(Backlog::new()),
+            total_received: Arc::new(AtomicU64::new(0)),
+            in_flight: Arc::new(InFlight::new(usize::MAX)),
+            rng: None,
+            scratch: Mutex::new(ReceiveScratch::new()),
+        }
+    }
+
+    /// Sample the weighted selection of this consumer from an RNG seeded with `seed`,
+    /// instead of from the thread local RNG.
+    ///
+    /// The same seed and the same sends and receives lead to the same selection sequence,
+    /// which makes tests and simulations reproducible. Every clone is a separate consumer
+    /// with its own RNG, seeded from the RNG of the multireceiver it was cloned from.
+    /// So clones created in the same order get the same seeds.
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.rng = Some(Mutex::new(SmallRng::seed_from_u64(seed)));
+        self
+    }
+
+    /// Reserve room for `groups` priority tiers and `channels` channels, so registering
+    /// that many channels at startup doesn't reallocate the tier list and the id lookup table.
+    pub fn with_capacity(self, groups: usize, channels: usize) -> Self {
+        {
+            let mut state = self.state.write().unwrap();
+            state.groups.reserve(groups);
+            state.positions.reserve(groups);
+            state.ready_tiers.grow(groups + 1);
+            state.lookup.reserve(channels);
+        }
+        self
+    }
+
+    /// Set the backoff strategy used by senders of channels created afterwards,
+    /// when they find their bounded channel full.
+    pub fn with_send_backoff(mut self, backoff: SendBackoff) -> Self {
+        self.send_backoff = backoff;
+        self
+    }
+
+    /// Balance channels of the same priority by the size of the messages they deliver,
+    /// instead of by the amount of messages.
+    ///
+    /// The size of every received message is determined by `size_fn` (e.g. its length in bytes).
+    /// receive() then always picks the channel that was served the least size relative to its weight,
+    /// so a channel with large messages is selected less often than one with small messages.
+    /// This replaces the random weighted selection with a deterministic one.
+    pub fn with_size_fairness(
+        mut self,
+        size_fn: impl Fn(&T) -> u64 + Send + Sync + 'static,
+    ) -> Self {
+        self.size_fn = Some(Arc::new(size_fn));
+        self
+    }
+
+    /// Set what receive() and receive_or_none() do while there are no channels, which is EmptyBehavior::Block by default.
+    ///
+    /// With EmptyBehavior::Panic or EmptyBehavior::ReturnNone, this applies both when they are called without
+    /// channels, e.g. because the consumer raced the channel setup, and when the last channel goes away while waiting.
+    /// Every clone of the DynMultiReceiver has its own behavior, which it inherits when cloned.
+    pub fn with_empty_behavior(mut self, behavior: EmptyBehavior) -> Self {
+        self.empty_behavior = behavior;
+        self
+    }
+
+    /// Set how receive() picks between channels of the same priority. Size fairness set with
+    /// with_size_fairness() takes precedence over the policy. Tiers can override it with set_group_policy().
+    pub fn with_policy(mut self, policy: Policy) -> Self {
+        self.policy = policy;
+        self
+    }
+
+    /// Compute defaults for every priority tier when its first channel is created, so tiers of
+    /// dynamically computed priorities don't need to be set up by hand.
+    ///
+    /// `defaults` returns the group weight and frozen default of the tier:
+    /// - The group weight is kept with the tier and returned by tier_weight(). Tiers are still
+    ///   always served in strict priority order, so it doesn't influence receive().
+    /// - If the frozen default is true, every channel created in the tier starts frozen,
+    ///   no matter the frozen state passed to new_channel(). It can be unfrozen as usual.
+    ///
+    /// Without tier defaults, tiers have a group weight of 1 and don't freeze their channels.
+    /// Once the last channel of a tier is removed, the tier is gone and recreated with fresh defaults.
+    /// `defaults` is called while holding the state lock, so it must not use the multichannel.
+    pub fn with_tier_defaults(
+        self,
+        defaults: impl Fn(&P) -> (u32, bool) + Send + Sync + 'static,
+    ) -> Self {
+        self.state.write().unwrap().tier_defaults = Some(Arc::new(defaults));
+        self
+    }
+
+    /// Change the priority of every channel to the one `f` computes from its current priority,
+    /// e.g. to make room for a new tier when the priority type changes.
+    ///
+    /// Channels keep their id, weight, frozen state and buffered messages, and senders keep working.
+    /// If `f` maps several priorities to the same one, their channels end up in a single tier,
+    /// which keeps the tier defaults of the highest old priority. A pinned priority is remapped too.
+    /// `f` is called while holding the write lock of the multichannel, so it must not use it.
+    pub fn remap_priorities(&self, f: impl Fn(&P) -> P) {
+        self.state.write().unwrap().remap_priorities(f);
+    }
+
+    /// Returns the priorities of all tiers with at least one unfrozen channel holding a message,
+    /// in the order receive() considers them.
+    ///
+    /// This is a snapshot: by the time it is returned, other consumers may have taken the messages.
+    pub fn receivable_priorities(&self) -> Vec<P>
+    where
+        P: Clone,
+    {
+        let state = self.state.read().unwrap();
+        let promotions = state.promotions().unwrap();
+        state
+            .scan_order()
+            .filter(|(group_idx, _)| state.has_deliverable(*group_idx, &promotions))
+            .map(|(_, group)| group.priority.clone())
+            .collect()
+    }
+
+    /// Returns the priority of the tier receive() would take the next message from,
+    /// or None if no message is deliverable. No message is taken.
+    ///
+    /// This is a snapshot: with other consumers or producers around, the result may be stale
+    /// by the time it is returned, so the next receive() can still be served from another tier.
+    pub fn peek_priority(&self) -> Option<P>
+    where
+        P: Clone,
+    {
+        let state = self.state.read().unwrap();
+        let promotions = state.promotions().unwrap();
+        let next = state
+            .scan_order()
+            .find(|(group_idx, _)| state.has_deliverable(*group_idx, &promotions))
+            .map(|(_, group)| group.priority.clone());
+        next
+    }
+
+    /// Returns the group weight of the tier with the given priority, or None if no channel has this priority.
+    pub fn tier_weight(&self, priority: &P) -> Option<u32> {
+        self.state.read().unwrap().group(priority).map(|g| g.weight)
+    }
+
+    /// Limit the amount of messages this consumer may hold unacknowledged via receive_tracked().
+    ///
+    /// Every clone of the DynMultiReceiver is a separate consumer with its own in-flight count,
+    /// which starts out at 0 and inherits the limit. The limit must be greater than 0.
+    pub fn with_in_flight_limit(mut self, limit: usize) -> Self {
+        assert!(limit > 0, "In-flight limit must be greater than 0");
+        self.in_flight = Arc::new(InFlight::new(limit));
+        self
+    }
+
+    /// Disable freezing for this multichannel, which must not have any channels yet.
+    ///
+    /// Freezing a channel, creating a frozen channel or restoring a snapshot with frozen channels
+    /// panics afterwards, and the frozen default of with_tier_defaults() is ignored. In exchange, a set ready bit always
+    /// belongs to a deliverable message, so sends into frozen channels can't make receive() scan a tier
+    /// in vain, and the scan doesn't look at the frozen flags of the channels at all. As tiers without a ready channel are skipped 64 at a time, receive() finds the highest priority
+    /// tier with a message in O(tiers / 64), no matter how many channels the tiers have.
+    pub fn without_freezing(self) -> Self {
+        {
+            let mut state = self.state.write().unwrap();
+            assert!(
+                state.lookup.is_empty(),
+                "Freezing must be disabled before creating channels"
+            );
+            state.freezing = false;
+        }
+        self
+    }
+
+    /// Count for every channel how often it was passed over, i.e. receive() picked a sibling of the same
+    /// priority while the channel had a deliverable message. The counts are returned by starvation_report().
+    ///
+    /// This is a fairness diagnostic and costs an atomic increment per passed over candidate, so it is off by default.
+    pub fn with_starvation_tracking(mut self) -> Self {
+        self.track_starvation = true;
+        self
+    }
+
+    /// Create a new channel with the given priority, weight, frozen state and optional bounds.
+    /// 
+    /// The weight is used to determine the probability of the channel being selected when calling receive()
+    /// on the DynMultiReceiver. The weight is relative to the weights of other channels in the same priority group.
+    /// The weight must be greater than 0.
+    /// 
+    /// The frozen state determines if the channel is considered when calling receive() on the DynMultiReceiver.
+    /// If the channel is frozen, it will not be considered, even if it has a message.
+    /// 
+    /// The bounds parameter is used to create a bounded channel. If None is passed, an unbounded channel is created.
+    /// If Some(bounds) is passed, a bounded channel with the given bounds is created.
+    pub fn new_channel(
+        &self,
+        priority: P,
+        weight: u32,
+        frozen: bool,
+        bounds: Option<usize>,
+    ) -> DynSender<T, P, S> {
+        let (sender, receiver) = match bounds {
+            Some(bounds) => crossbeam_channel::bounded(bounds),
+            None => crossbeam_channel::unbounded(),
+        };
+        self.register_channel(priority, weight, frozen, sender, receiver, None, None)
+    }
+
+    /// Create a new channel like new_channel(), which can also be addressed by its name.
+    ///
+    /// Names are unique, this fails if a live channel already has the name.
+    /// The name is released once the channel is removed.
+    pub fn new_named_channel(
+        &self,
+        name: impl Into<String>,
+        priority: P,
+        weight: u32,
+        frozen: bool,
+        bounds: Option<usize>,
+    ) -> Result<DynSender<T, P, S>, NameTakenError> {
+        let (sender, receiver) = match bounds {
+            Some(bounds) => crossbeam_channel::bounded(bounds),
+            None => crossbeam_channel::unbounded(),
+        };
+        self.try_register_channel(
+            Some(name.into()),
+            priority,
+            weight,
+            frozen,
+            sender,
+            receiver,
+            None,
+            None,
+        )
+    }
+
+    /// Returns the id of the channel with the given name, if it exists.
+    pub fn channel_id_by_name(&self, name: &str) -> Option<u32> {
+        self.state.read().unwrap().names.get(name).copied()
+    }
+
+    /// Create a new channel like new_channel() and enqueue `first` into it.
+    ///
+    /// The message is enqueued before the channel becomes visible to receivers, so no receiver
+    /// can ever observe the channel empty. This is handy for priming a reply channel.
+    /// Panics if `bounds` is Some(0), as a zero capacity channel can't hold a message.
+    pub fn new_channel_with_first(
+        &self,
+        priority: P,
+        weight: u32,
+        frozen: bool,
+        bounds: Option<usize>,
+        first: T,
+    ) -> DynSender<T, P, S> {
+        assert!(
+            bounds != Some(0),
+            "Bounds must be greater than 0 to hold the first message"
+        );
+        let (sender, receiver) = match bounds {
+            Some(bounds) => crossbeam_channel::bounded(bounds),
+            None => crossbeam_channel::unbounded(),
+        };
+        let envelope = Envelope {
+            seq: self.sequence.fetch_add(1, Ordering::Relaxed),
+            value: first,
+        };
+        if sender.try_send(envelope).is_err() {
+            unreachable!("The new channel has room for the first message");
+        }
+        let counted = self.backlog.count();
+        let sender = self.register_channel(priority, weight, frozen, sender, receiver, None, None);
+        sender.wake_receiver();
+        sender.on_sent(counted);
+        sender
+    }
+
+    /// Create a new channel that accepts at most `max_messages` sends.
+    ///
+    /// Further sends fail with SendError::Exhausted. Once all messages were sent and received,
+    /// the channel removes itself. This is handy for single use reply channels.
+    /// The other parameters behave like in new_channel().
+    pub fn new_limited_channel(
+        &self,
+        priority: P,
+        weight: u32,
+        frozen: bool,
+        bounds: Option<usize>,
+        max_messages: u64,
+    ) -> DynSender<T, P, S> {
+        let (sender, receiver) = match bounds {
+            Some(bounds) => crossbeam_channel::bounded(bounds),
+            None => crossbeam_channel::unbounded(),
+        };
+        let limit = Arc::new(SendLimit {
+            max: max_messages,
+            reserved: AtomicU64::new(0),
+            sent: AtomicU64::new(0),
+        });
+        self.register_channel(
+            priority,
+            weight,
+            frozen,
+            sender,
+            receiver,
+            None,
+            Some(limit),
+        )
+    }
+
+    /// Create a new bounded channel with a main capacity and an additional reserve capacity.
+    ///
+    /// Normal send() calls block once `main` messages are buffered, while send_urgent() may
+    /// also use the last `reserve` slots. This keeps control messages from getting stuck
+    /// behind a bulk backlog. The main capacity must be greater than 0.
+    pub fn new_channel_with_reserve(
+        &self,
+        priority: P,
+        weight: u32,
+        frozen: bool,
+        main: usize,
+        reserve: usize,
+    ) -> DynSender<T, P, S> {
+        assert!(main > 0, "Main capacity must be greater than 0");
+        let (sender, receiver) = crossbeam_channel::bounded(main + reserve);
+        let gate = Arc::new(ReserveGate {
+            main,
+            lock: Mutex::new(false),
+            condvar: Condvar::new(),
+        });
+        self.register_channel(priority, weight, frozen, sender, receiver, Some(gate), None)
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn register_channel(
+        &self,
+        priority: P,
+        weight: u32,
+        frozen: bool,
+        sender: crossbeam_channel::Sender<Envelope<T>>,
+        receiver: crossbeam_channel::Receiver<Envelope<T>>,
+        reserve: Option<Arc<ReserveGate>>,
+        limit: Option<Arc<SendLimit>>,
+    ) -> DynSender<T, P, S> {
+        match self.try_register_channel(
+            None, priority, weight, frozen, sender, receiver, reserve, limit,
+        ) {
+            Ok(sender) => sender,
+            Err(_) => unreachable!("Only named channels can clash"),
+        }
+    }
+
+    /// Like register_channel(), but fails if a channel with the given name already exists.
+    #[allow(clippy::too_many_arguments)]
+    fn try_register_channel(
+        &self,
+        name: Option<String>,
+        priority: P,
+        weight: u32,
+        frozen: bool,
+        sender: crossbeam_channel::Sender<Envelope<T>>,
+        receiver: crossbeam_channel::Receiver<Envelope<T>>,
+        reserve: Option<Arc<ReserveGate>>,
+        limit: Option<Arc<SendLimit>>,
+    ) -> Result<DynSender<T, P, S>, NameTakenError> {
+        assert!(weight > 0, "Weight must be greater than 0");
+        self.state.read().unwrap().check_freezing(frozen);
+        // Allocated before taking the write lock, which waits for running receive() scans and blocks freeze toggles.
+        // The id is assigned under the lock.
+        let mut receiver = DynReceiver::new(
+            0,
+            weight,
+            frozen,
+            reserve.clone(),
+            limit.clone(),
+            self.backlog.clone(),
+            receiver,
+        );
+        let activity = receiver.activity.clone();
+        let handover = receiver.handover.clone();
+        let senders = receiver.senders.clone();
+        let closed = receiver.closed.clone();
+        let (id, ready) = {
+            let mut state = self.state.write().unwrap();
+            if let Some(name) = &name {
+                if state.names.contains_key(name) {
+                    return Err(NameTakenError(name.clone()));
+                }
+            }
+            receiver.id = state.alloc_id();
+            if let Some(name) = name {
+                state.names.insert(name.clone(), receiver.id);
+                receiver.name = Some(name);
+            }
+            (receiver.id, state.add_receiver(priority, receiver))
+        };
+        {
+            // Taking the mutex keeps the notification from slipping in between
+            // the check and the wait of wait_for_channel()
+            let _guard = self.channel_added.0.lock().unwrap();
+            self.channel_added.1.notify_all();
+        }
+        Ok(DynSender {
+            id,
+            count_multireceivers: self.amount_multireceivers.clone(),
+            cleanup: self.cleanup.clone(),
+            condvar: self.condvar.clone(),
+            state: self.state.clone(),
+            sequence: self.sequence.clone(),
+            backoff: self.send_backoff,
+            backlog: self.backlog.clone(),
+            ready,
+            activity,
+            reserve,
+            limit,
+            handover,
+            senders,
+            closed,
+            inner: sender,
+        })
+    }
+
+    /// Remove the channel with the given name, see remove_channel_by_id().
+    /// Returns false if there was no such channel.
+    pub fn remove_channel_by_name(&self, name: &str) -> bool {
+        let removed = {
+            let mut state = self.state.write().unwrap();
+            match state.names.get(name) {
+                Some(&id) => state.remove_receiver(id),
+                None => None,
+            }
+        };
+        if removed.is_none() {
+            return false;
+        }
+        drop(removed);
+        self.wake_if_no_channels().unwrap();
+        true
+    }
+
+    /// Remove the channel with the given id, dropping its buffered messages.
+    /// Senders blocked in send() on this channel return SendError::Disconnected right away.
+    ///
+    /// If a RawReceiver of the channel exists, the buffered messages stay there instead,
+    /// and the senders keep sending into it until the RawReceiver is dropped as well, see RawReceiver.
+    ///
+    /// Returns false if there was no such channel, e.g. because it was already removed
+    /// by another consumer or cleaned up after its sender disconnected.
+    pub fn remove_channel_by_id(&self, id: u32) -> bool {
+        let removed = self.state.write().unwrap().remove_receiver(id);
+        if removed.is_none() {
+            return false;
+        }
+        drop(removed);
+        self.wake_if_no_channels().unwrap();
+        true
+    }
+
+    /// Remove the channel of the given sender, see remove_channel_by_id().
+    /// A newer channel that reused the id of the already removed channel is left alone.
+    pub fn remove_channel(&self, sender: &DynSender<T, P, S>) -> bool {
+        let removed = {
+            let mut state = self.state.write().unwrap();
+            match sender.own_receiver(&state) {
+                Some(_) => state.remove_receiver(sender.id),
+                None => None,
+            }
+        };
+        if removed.is_none() {
+            return false;
+        }
+        drop(removed);
+        self.wake_if_no_channels().unwrap();
+        true
+    }
+
+    /// Like remove_channel(), but returns the buffered messages in the order they were sent instead of dropping them,
+    /// e.g. to move them to another channel. Returns an empty Vec if the channel was already removed.
+    pub fn remove_channel_draining(&self, sender: &DynSender<T, P, S>) -> Vec<T> {
+        let removed = {
+            let mut state = self.state.write().unwrap();
+            match sender.own_receiver(&state) {
+                Some(_) => state.remove_receiver(sender.id),
+                None => None,
+            }
+        };
+        let Some(removed) = removed else {
+            return Vec::new();
+        };
+        let mut drained = Vec::with_capacity(removed.len());
+        while let Ok(value) = removed.try_recv() {
+            drained.push(value);
+        }
+        // Dropping the receiver only accounts for the messages still left in it
+        self.backlog.sub(drained.len());
+        drop(removed);
+        self.wake_if_no_channels().unwrap();
+        drained
+    }
+
+    /// Remove all channels without a send or receive for longer than `idle_for`, returning their ids.
+    ///
+    /// A channel that was never used counts as idle since its creation.
+    /// Messages still buffered in a reaped channel are dropped together with it.
+    pub fn reap_idle(&self, idle_for: Duration) -> Vec<u32> {
+        let mut removed = Vec::new();
+        let mut state = self.state.write().unwrap();
+        let ids: Vec<u32> = state
+            .groups
+            .iter()
+            .flat_map(|g| &g.receivers)
+            .filter(|r| r.activity.idle_for() > idle_for)
+            .map(|r| r.id)
+            .collect();
+        for &id in &ids {
+            removed.extend(state.remove_receiver(id));
+        }
+        drop(state);
+        drop(removed);
+        if !ids.is_empty() {
+            self.wake_if_no_channels().unwrap();
+        }
+        ids
+    }
+
+    /// Remove all channels that won't deliver another message, because their senders were dropped
+    /// or they used up their sends, returning their ids.
+    ///
+    /// Such channels are also cleaned up by the next receive(), this lets an idle consumer trim them in the meantime.
+    pub fn reap_disconnected(&self) -> Vec<u32> {
+        let mut removed = Vec::new();
+        let mut state = self.state.write().unwrap();
+        let ids: Vec<u32> = state
+            .groups
+            .iter()
+            .flat_map(|g| &g.receivers)
+            .filter(|r| r.is_finished())
+            .map(|r| r.id)
+            .collect();
+        for &id in &ids {
+            removed.extend(state.remove_receiver(id));
+        }
+        drop(state);
+        drop(removed);
+        if !ids.is_empty() {
+            self.wake_if_no_channels().unwrap();
+        }
+        ids
+    }
+
+    /// Release the memory held on to after many channels were removed, e.g. after a burst of short-lived channels.
+    /// Shrinks the tier list, the channel lists of the tiers, the id lookup table and the cleanup set.
+    pub fn shrink_to_fit(&self) {
+        let mut state = self.state.write().unwrap();
+        state.groups.shrink_to_fit();
+        for group in &mut state.groups {
+            group.shrink_to_fit();
+        }
+        state.positions.shrink_to_fit();
+        state.free_keys.shrink_to_fit();
+        state.lookup.shrink_to_fit();
+        state.links.shrink_to_fit();
+        state.names.shrink_to_fit();
+        self.cleanup.1.lock().unwrap().shrink_to_fit();
+    }
+
+    fn schedule_cleanup(&self, id: u32) -> Result<(), RecvError> {
+        self.cleanup.1.lock()?.insert(id);
+        self.cleanup.0.store(true, Ordering::Relaxed);
+        Ok(())
+    }
+
+    /// Removes all channels scheduled for cleanup. Must not be called while holding the state lock.
+    fn run_cleanup(&self) -> Result<(), RecvError> {
+        if self.cleanup.0.fetch_and(false, Ordering::Relaxed) {
+            let mut removed = Vec::new();
+            let mut state = self.state.write()?;
+            let mut to_clean = self.cleanup.1.lock()?;
+            for id in to_clean.drain() {
+                // A channel whose sender was dropped is only removed once its messages are delivered,
+                // after_take() schedules it again then. Checking is_finished() instead of just the id also
+                // keeps a newer channel that reused the id of a removed one.
+                if state.receiver(id).is_some_and(|r| !r.is_finished()) {
+                    continue;
+                }
+                // The channel may have been removed explicitly since it was scheduled
+                removed.extend(state.try_remove_receiver(id)?);
+            }
+            drop(to_clean);
+            drop(state);
+            if !removed.is_empty() {
+                self.wake_if_no_channels()?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Wakes up a waiting receiver once the last channel is gone, so iter() can end.
+    /// Must not be called while holding the state lock.
+    fn wake_if_no_channels(&self) -> Result<(), RecvError> {
+        if self.state.read()?.is_empty() {
+            wake_all(&self.condvar, 1);
+        }
+        Ok(())
+    }
+
+    /// Blocks until a wake token is available and takes it.
+    fn wait_for_wake(&self) -> Result<(), RecvError> {
+        self.wait_for_wake_until(None).map(|_| ())
+    }
+
+    /// Like wait_for_wake(), but gives up at the deadline, if any. Returns false if it gave up.
+    fn wait_for_wake_until(&self, deadline: Option<Instant>) -> Result<bool, RecvError> {
+        let (lock, condvar) = &*self.condvar;
+        let mut wakes = lock.lock()?;
+        wakes.waiting += 1;
+        let woken = loop {
+            if wakes.pending > 0 {
+                wakes.pending -= 1;
+                break true;
+            }
+            let waited = match deadline {
+                None => condvar.wait(wakes).map_err(|err| err.into_inner()),
+                Some(deadline) => {
+                    let now = Instant::now();
+                    if now >= deadline {
+                        break false;
+                    }
+                    condvar
+                        .wait_timeout(wakes, deadline - now)
+                        .map(|(wakes, _)| wakes)
+                        .map_err(|err| err.into_inner().0)
+                }
+            };
+            wakes = match waited {
+                Ok(wakes) => wakes,
+                Err(mut wakes) => {
+                    wakes.waiting -= 1;
+                    return Err(RecvError::Poisoned);
+                }
+            };
+        };
+        wakes.waiting -= 1;
+        Ok(woken)
+    }
+
+    /// Blocks until the wake epoch moved past `epoch`, without taking a token.
+    fn wait_for_epoch(&self, epoch: u64) -> Result<(), RecvError> {
+        let (lock, condvar) = &*self.condvar;
+        let mut wakes = lock.lock()?;
+        wakes.selective += 1;
+        while wakes.epoch == epoch {
+            wakes = match condvar.wait(wakes) {
+                Ok(wakes) => wakes,
+                Err(err) => {
+                    err.into_inner().selective -= 1;
+                    return Err(RecvError::Poisoned);
+                }
+            };
+        }
+        wakes.selective -= 1;
+        Ok(())
+    }
+
+    /// Takes the wake tokens of messages that were delivered without waiting, as far as there are any.
+    fn take_wakes(&self, amount: usize) -> Result<(), RecvError> {
+        let mut wakes = self.condvar.0.lock()?;
+        wakes.pending = wakes.pending.saturating_sub(amount);
+        Ok(())
+    }
+
+    /// Receive the next message, blocking until one is deliverable.
+    ///
+    /// By default, this keeps waiting while there are no channels, e.g. until the first producer registered.
+    /// Use recv() to notice that all channels are gone, or see with_empty_behavior().
+    pub fn receive(&self) -> T {
+        match self.empty_behavior {
+            EmptyBehavior::Block => self.recv_with_rng(&mut self.rng()).unwrap(),
+            EmptyBehavior::Panic | EmptyBehavior::ReturnNone => self
+                .recv_until_no_channels()
+                .unwrap()
+                .expect("receive() was called while there are no channels"),
+        }
+    }
+
+    /// Like receive(), but returns None instead of waiting while there are no channels,
+    /// if the empty behavior is EmptyBehavior::ReturnNone. Otherwise, this behaves exactly like receive().
+    pub fn receive_or_none(&self) -> Option<T> {
+        match self.empty_behavior {
+            EmptyBehavior::Block | EmptyBehavior::Panic => Some(self.receive()),
+            EmptyBehavior::ReturnNone => self.recv_until_no_channels().unwrap(),
+        }
+    }
+
+    /// Like receive(), but returns an error instead of panicking if the multichannel is in a broken state,
+    /// and RecvError::Disconnected instead of waiting once there are no channels left.
+    ///
+    /// Removing all channels, or dropping all senders once their messages are delivered, is the signal
+    /// to shut down: receivers waiting in recv() wake up and return RecvError::Disconnected.
+    /// So recv() also returns it before the first channel was created.
+    ///
+    /// Soft failures, which are reported as an error:
+    /// - A lock was poisoned, because another thread panicked while holding it. Locks whose data a panic
+    ///   can't leave inconsistent, like the one a panicking receive_matching() predicate held, are recovered instead.
+    /// - The id lookup table doesn't match the registered channels while cleaning up disconnected channels
+    ///   or resolving priority links.
+    /// - The weights of the candidate channels can't be sampled.
+    ///
+    /// A channel scheduled for cleanup that was already removed is not an error.
+    /// Everything else, e.g. misuse of the public API like a channel weight of 0, still panics.
+    pub fn recv(&self) -> Result<T, RecvError> {
+        self.recv_until_no_channels()?
+            .ok_or(RecvError::Disconnected)
+    }
+
+    /// Like receive(), but returns TryReceiveError::Empty right away instead of blocking
+    /// if no unfrozen channel has a message.
+    pub fn try_receive(&self) -> Result<T, TryReceiveError> {
+        self.run_cleanup().unwrap();
+        let value = loop {
+            let state = self.state.read().unwrap();
+            match self.select(&state, &mut self.rng(), |_| true).unwrap() {
+                Selection::Found(selected) => break selected.value,
+                // A sender is about to hand over its message, so there is one to receive
+                Selection::Rendezvous => {
+                    drop(state);
+                    std::thread::yield_now();
+                }
+                Selection::Empty => return Err(TryReceiveError::Empty),
+            }
+        };
+        self.take_wakes(1).unwrap();
+        self.on_delivered(1);
+        Ok(value)
+    }
+
+    /// Like try_receive(), but only considers channels with a priority of at least `floor`, i.e. `priority <= floor`
+    /// as smaller values are more urgent (see the crate docs). Messages of less urgent channels are ignored,
+    /// even if they are the only ones buffered.
+    ///
+    /// Returns None right away if none of these channels has a deliverable message.
+    /// A channel counts with the priority it was created with or moved to, like in receive_with().
+    pub fn receive_from_priority_at_least(&self, floor: P) -> Option<T> {
+        self.run_cleanup().unwrap();
+        let value = loop {
+            let state = self.state.read().unwrap();
+            let urgent = |receiver: &DynReceiver<T>| {
+                state
+                    .locate(receiver.id)
+                    .is_some_and(|(group_idx, _)| state.groups[group_idx].priority <= floor)
+            };
+            match self.select(&state, &mut self.rng(), urgent).unwrap() {
+                Selection::Found(selected) => break selected.value,
+                // A sender is about to hand over its message, so there is one to receive
+                Selection::Rendezvous => {
+                    drop(state);
+                    std::thread::yield_now();
+                }
+                Selection::Empty => return None,
+            }
+        };
+        self.take_wakes(1).unwrap();
+        self.on_delivered(1);
+        Some(value)
+    }
+
+    /// Returns an iterator that receives messages like receive(), until there are no channels left.
+    ///
+    /// A channel is gone once it is removed, or once all clones of its sender were dropped and all of
+    /// its messages were delivered. So the iterator ends after every producer dropped its sender and
+    /// the backlog is drained. Until then, it blocks waiting for messages like receive().
+    /// Don't create the iterator before the first channel, it would end right away.
+    pub fn iter(&self) -> Iter<'_, T, P, S> {
+        Iter { receiver: self }
+    }
+
+    /// Like receive(), but waits for a message by yielding to the async runtime instead of blocking the thread.
+    ///
+    /// Every send, unfreeze or channel removal wakes all tasks waiting here, which then try to receive
+    /// without blocking, so prefer few consumer tasks. A rendezvous with a sender of a zero capacity channel
+    /// still blocks for the moment the handover takes. Requires the async feature.
+    #[cfg(feature = "async")]
+    pub async fn receive_async(&self) -> T {
+        ReceiveFuture { receiver: self }.await
+    }
+
+    /// Turn this multireceiver into an async stream of messages, which ends once there are no channels left.
+    /// The stream implements futures_core::Stream. Requires the async feature.
+    ///
+    /// Like iter(), don't create the stream before the first channel, it would end right away.
+    #[cfg(feature = "async")]
+    pub fn into_stream(self) -> MultiStream<T, P, S> {
+        MultiStream { receiver: self }
+    }
+
+    /// Tries to receive a message without blocking, registering the waker of the task if there is none.
+    /// With `stop_without_channels`, returns Ready(None) once there are no channels left.
+    #[cfg(feature = "async")]
+    fn poll_receive(
+        &self,
+        cx: &mut std::task::Context<'_>,
+        stop_without_channels: bool,
+    ) -> std::task::Poll<Option<T>> {
+        let lock = &self.condvar.0;
+        loop {
+            let epoch = lock.lock().unwrap().epoch;
+            if let Ok(value) = self.try_receive() {
+                return std::task::Poll::Ready(Some(value));
+            }
+            if stop_without_channels && self.no_channels() {
+                return std::task::Poll::Ready(None);
+            }
+            let mut wakes = lock.lock().unwrap();
+            // Otherwise something changed while trying, so try again
+            if wakes.epoch == epoch {
+                if !wakes.tasks.0.iter().any(|task| task.will_wake(cx.waker())) {
+                    wakes.tasks.0.push(cx.waker().clone());
+                }
+                return std::task::Poll::Pending;
+            }
+        }
+    }
+
+    /// Receives a message, but returns None instead of waiting once there are no channels left.
+    fn recv_until_no_channels(&self) -> Result<Option<T>, RecvError> {
+        self.with_scratch(|scratch| {
+            self.recv_selected_until(&mut self.rng(), scratch, None, true, None, |_, selected| {
+                selected.value
+            })
+        })
+    }
+
+    /// Returns an iterator that receives messages with try_receive() until none is deliverable.
+    ///
+    /// This drains the current backlog in the usual priority and weight order without blocking.
+    /// Messages in frozen channels are left alone.
+    pub fn try_iter(&self) -> TryIter<'_, T, P, S> {
+        TryIter { receiver: self }
+    }
+
+    /// Returns a channel that gets a () whenever a message may have become deliverable, so this multichannel
+    /// can be watched in an external crossbeam_channel::Select loop. After a signal, take the messages with try_iter().
+    ///
+    /// - Signals coalesce: the channel holds at most one, which can stand for any amount of messages.
+    ///   So drain all deliverable messages after a signal, not just one.
+    /// - Sends into frozen channels fire a signal as well, which then finds nothing to receive.
+    ///   Unfreezing a channel with buffered messages fires a signal.
+    /// - Other consumers may take the messages first, so try_receive() can return Empty after a signal.
+    /// - The signal fires right away if there are buffered messages when it is created.
+    ///
+    /// The signal is dropped from the multichannel once the returned receiver is dropped.
+    pub fn recv_ready_signal(&self) -> crossbeam_channel::Receiver<()> {
+        let (signal, ready) = crossbeam_channel::bounded(1);
+        self.condvar.0.lock().unwrap().signals.push(signal.clone());
+        // Checked after registering, so a message sent in between can't be missed
+        if !self.is_empty() {
+            let _ = signal.try_send(());
+        }
+        ready
+    }
+
+    /// Returns a receiver of the channels created and removed from now on, e.g. to keep a dashboard up to date.
+    /// Every call returns a new subscriber, which gets all events.
+    ///
+    /// Events are buffered without a bound, so a slow subscriber never blocks the multichannel.
+    /// The subscription ends once the returned receiver is dropped.
+    pub fn events(&self) -> crossbeam_channel::Receiver<ChannelEvent<P>>
+    where
+        P: Clone + Send + 'static,
+    {
+        let (sender, events) = crossbeam_channel::unbounded();
+        let subscriber: EventSubscriber<P> = Box::new(move |event| {
+            let event = match event {
+                ChannelEvent::Added { id, priority } => ChannelEvent::Added {
+                    id,
+                    priority: priority.clone(),
+                },
+                ChannelEvent::Removed { id } => ChannelEvent::Removed { id },
+            };
+            sender.send(event).is_ok()
+        });
+        self.state.write().unwrap().subscribers.push(subscriber);
+        events
+    }
+
+    /// Like receive(), but gives up once `timeout` has passed without a deliverable message.
+    ///
+    /// Messages in frozen channels don't count, so the timeout is honored even while all buffered
+    /// messages are frozen. If there is no channel left, RecvTimeoutError::Disconnected is returned
+    /// right away, also when the last channel is removed while waiting.
+    pub fn receive_timeout(&self, timeout: Duration) -> Result<T, RecvTimeoutError> {
+        let deadline = Instant::now().checked_add(timeout);
+        let value = self
+            .with_scratch(|scratch| {
+                self.recv_selected_until(
+                    &mut self.rng(),
+                    scratch,
+                    deadline,
+                    true,
+                    None,
+                    |_, selected| selected.value,
+                )
+            })
+            .unwrap();
+        match value {
+            Some(value) => Ok(value),
+            None if self.no_channels() => Err(RecvTimeoutError::Disconnected),
+            None => Err(RecvTimeoutError::Timeout),
+        }
+    }
+
+    /// Like receive(), but uses the given RNG for the weighted selection of this call.
+    ///
+    /// With a seeded RNG the selection sequence is deterministic, which is mostly useful for tests.
+    /// Other consumers keep using their own RNG.
+    pub fn receive_with_rng(&self, rng: &mut impl RngCore) -> T {
+        self.recv_with_rng(rng).unwrap()
+    }
+
+    /// Like receive(), but keeps the candidates of the weighted selection in the given scratch buffers.
+    ///
+    /// Useful when several threads receive through the same consumer, see ReceiveScratch.
+    /// Selection behaves exactly like receive().
+    pub fn receive_with_scratch(&self, scratch: &mut ReceiveScratch) -> T {
+        self.recv_selected(&mut self.rng(), scratch, |_, selected| selected.value)
+            .unwrap()
+    }
+
+    /// Like receive(), but serves the channels of the highest priority group with a message in round robin
+    /// order, as with Policy::RoundRobin, no matter the configured policy, size fairness or weights.
+    ///
+    /// The position in the round robin is kept per group and shared with receive() under Policy::RoundRobin,
+    /// so strict receives of all consumers together cycle through the channels in the order of their ids.
+    /// No RNG is involved.
+    pub fn receive_strict(&self) -> T {
+        self.with_scratch(|scratch| {
+            self.recv_selected_until(
+                &mut self.rng(),
+                scratch,
+                None,
+                false,
+                Some(Policy::RoundRobin),
+                |_, selected| selected.value,
+            )
+        })
+        .unwrap()
+        .expect("a receive without a deadline can't time out")
+    }
+
+    /// Take the next message of the channel with the given id without blocking, bypassing the priority
+    /// and weight selection. Frozen channels are drained as well, which is handy for diagnostics.
+    pub fn try_recv_from(&self, id: u32) -> Result<T, TryRecvFromError> {
+        let value = {
+            let state = self.state.read().unwrap();
+            let receiver = state.receiver(id).ok_or(TryRecvFromError::NotFound)?;
+            let value = receiver.try_recv().map_err(|error| match error {
+                crossbeam_channel::TryRecvError::Empty => TryRecvFromError::Empty,
+                crossbeam_channel::TryRecvError::Disconnected => TryRecvFromError::Disconnected,
+            })?;
+            self.after_take(receiver).unwrap();
+            value
+        };
+        self.take_wakes(1).unwrap();
+        self.on_delivered(1);
+        Ok(value)
+    }
+
+    /// Returns direct access to the buffer of the channel with the given id, e.g. to use it in an own
+    /// crossbeam_channel::Select loop. Returns None if there is no such channel.
+    ///
+    /// **Pulling from the RawReceiver bypasses the priority scheduler and can starve all other channels**,
+    /// see RawReceiver for the details.
+    pub fn raw_receiver(&self, id: u32) -> Option<RawReceiver<T>> {
+        let state = self.state.read().unwrap();
+        let receiver = state.receiver(id)?;
+        Some(RawReceiver {
+            buffer: receiver.buffer.clone(),
+            activity: receiver.activity.clone(),
+            reserve: receiver.reserve.clone(),
+            backlog: self.backlog.clone(),
+            total_received: self.total_received.clone(),
+        })
+    }
+
+    /// Like receive(), but also returns the id of the channel the message came from.
+    ///
+    /// The id is the one DynSender::id() returns for that channel.
+    pub fn receive_with_id(&self) -> (u32, T) {
+        self.recv_tagged(&mut self.rng()).unwrap()
+    }
+
+    fn rng(&self) -> ConsumerRng<'_> {
+        match &self.rng {
+            Some(rng) => ConsumerRng::Seeded(rng),
+            None => ConsumerRng::Thread(rand::thread_rng()),
+        }
+    }
+
+    fn recv_with_rng(&self, rng: &mut impl RngCore) -> Result<T, RecvError> {
+        self.recv_tagged(rng).map(|(_, value)| value)
+    }
+
+    /// Runs `f` with the scratch buffers of this consumer,
+    /// or with fresh ones while another thread receives through the same consumer.
+    fn with_scratch<R>(&self, f: impl FnOnce(&mut ReceiveScratch) -> R) -> R {
+        match self.scratch.try_lock() {
+            Ok(mut scratch) => f(&mut scratch),
+            Err(_) => f(&mut ReceiveScratch::new()),
+        }
+    }
+
+    /// Receives a message together with the id of the channel it came from.
+    fn recv_tagged(&self, rng: &mut impl RngCore) -> Result<(u32, T), RecvError> {
+        self.with_scratch(|scratch| {
+            self.recv_selected(rng, scratch, |_, selected| {
+                (selected.receiver.id, selected.value)
+            })
+        })
+    }
+
+    /// Receives a message and maps the selection with `f`, while still holding the state lock.
+    fn recv_selected<U>(
+        &self,
+        rng: &mut impl RngCore,
+        scratch: &mut ReceiveScratch,
+        f: impl FnOnce(&DynState<T, P, S>, Selected<'_, T>) -> U,
+    ) -> Result<U, RecvError> {
+        self.recv_selected_until(rng, scratch, None, false, None, f)
+            .map(|value| value.expect("a receive without a deadline can't time out"))
+    }
+
+    /// Like recv_selected(), but gives up at the deadline, if any, returning None.
+    /// With `stop_without_channels`, it also gives up once there are no channels left.
+    fn recv_selected_until<U>(
+        &self,
+        rng: &mut impl RngCore,
+        scratch: &mut ReceiveScratch,
+        deadline: Option<Instant>,
+        stop_without_channels: bool,
+        policy_override: Option<Policy>,
+        f: impl FnOnce(&DynState<T, P, S>, Selected<'_, T>) -> U,
+    ) -> Result<Option<U>, RecvError> {
+        let mut woken = false;
+        let value = loop {
+            // Channels of dropped senders may have been scheduled while waiting
+            self.run_cleanup()?;
+            {
+                let state = self.state.read()?;
+                match self.select_with(
+                    &state,
+                    rng,
+                    |_| true,
+                    DynReceiver::try_recv,
+                    scratch,
+                    policy_override,
+                )? {
+                    Selection::Found(selected) => break f(&state, selected),
+                    // A sender is about to hand over its message, which takes only a moment
+                    Selection::Rendezvous => {
+                        drop(state);
+                        if deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+                            if woken {
+                                // Hand the wake back to whoever receives the message in the end
+                                wake_all(&self.condvar, 1);
+                            }
+                            return Ok(None);
+                        }
+                        std::thread::yield_now();
+                        continue;
+                    }
+                    Selection::Empty if stop_without_channels && state.is_empty() => {
+                        drop(state);
+                        if woken {
+                            // Pass the wake on, so other waiting receivers notice as well
+                            wake_all(&self.condvar, 1);
+                        }
+                        return Ok(None);
+                    }
+                    Selection::Empty => {}
+                }
+            }
+            // Nothing deliverable, e.g. all buffered messages are in frozen channels.
+            // Wait for the next send or unfreeze and re-scan.
+            if !self.wait_for_wake_until(deadline)? {
+                return Ok(None);
+            }
+            woken = true;
+        };
+        if !woken {
+            self.take_wakes(1)?;
+        }
+        self.on_delivered(1);
+        Ok(Some(value))
+    }
+
+    /// Receives a message from one of the channels for which `filter` returns true, taking it with `take`,
+    /// and maps the selection with `f` while still holding the state lock.
+    ///
+    /// Unlike recv_selected(), this doesn't wait for a wake token, as the token may belong to a message
+    /// the filter excludes. It waits for the next wake of any kind instead and re-scans.
+    fn recv_filtered<U>(
+        &self,
+        rng: &mut impl RngCore,
+        filter: impl Fn(&DynReceiver<T>) -> bool,
+        take: impl Fn(&DynReceiver<T>) -> Result<T, crossbeam_channel::TryRecvError>,
+        f: impl FnOnce(&DynState<T, P, S>, Selected<'_, T>) -> U,
+    ) -> Result<U, RecvError> {
+        self.run_cleanup()?;
+        let mut scratch = self.scratch.try_lock().ok();
+        let mut fresh = ReceiveScratch::new();
+        let scratch = scratch.as_deref_mut().unwrap_or(&mut fresh);
+        let value = loop {
+            let epoch = self.condvar.0.lock()?.epoch;
+            {
+                let state = self.state.read()?;
+                match self.select_with(&state, rng, &filter, &take, scratch, None)? {
+                    Selection::Found(selected) => break f(&state, selected),
+                    Selection::Rendezvous => {
+                        drop(state);
+                        std::thread::yield_now();
+                        continue;
+                    }
+                    Selection::Empty => {}
+                }
+            }
+            self.wait_for_epoch(epoch)?;
+        };
+        self.take_wakes(1)?;
+        self.on_delivered(1);
+        Ok(value)
+    }
+
+    /// Like receive(), but also returns an Ack that keeps the message in flight until it is acknowledged or dropped.
+    ///
+    /// If this consumer already holds as many unacknowledged messages as its limit set with
+    /// with_in_flight_limit(), this blocks until one of them is acknowledged, before waiting for a message.
+    /// This bounds the amount of work a single consumer has going on at the same time.
+    pub fn receive_tracked(&self) -> (T, Ack) {
+        let in_flight = &self.in_flight;
+        {
+            let mut count = in_flight.count.lock().unwrap();
+            while *count >= in_flight.limit {
+                count = in_flight.condvar.wait(count).unwrap();
+            }
+            *count += 1;
+        }
+        let ack = Ack {
+            in_flight: in_flight.clone(),
+        };
+        (self.receive(), ack)
+    }
+
+    /// Returns the amount of messages this consumer received with receive_tracked() and didn't acknowledge yet.
+    pub fn in_flight(&self) -> usize {
+        *self.in_flight.count.lock().unwrap()
+    }
+
+    /// Like receive(), but only considers channels with an id below `cutoff_id`.
+    ///
+    /// Channel ids are assigned in increasing order, so this only drains channels created before
+    /// the channel with id `cutoff_id`, which is handy to split old and new channels during a reconfiguration.
+    /// Once the ids wrapped around after u32::MAX (see DynSender::id()), newer channels may get lower ids
+    /// and the ids no longer tell the creation order.
+    /// Blocks while none of these channels has a deliverable message, even if newer channels have some.
+    pub fn receive_created_before(&self, cutoff_id: u32) -> T {
+        self.recv_filtered(
+            &mut self.rng(),
+            |receiver| receiver.id < cutoff_id,
+            DynReceiver::try_recv,
+            |_, selected| selected.value,
+        )
+        .unwrap()
+    }
+
+    /// Like receive(), but only takes a message for which `pred` returns true, leaving the others buffered.
+    ///
+    /// Only the head of every channel is looked at: a channel whose next message doesn't match is skipped,
+    /// even if a later message would. So this returns the highest priority matching head of a channel,
+    /// picked between channels of the same priority like receive() does, and blocks while there is none.
+    /// A rejected head stays first in its channel and is delivered by the next receive() from it.
+    ///
+    /// `pred` is called while holding the read lock of the multichannel, so it must not use it.
+    pub fn receive_matching(&self, pred: impl Fn(&T) -> bool) -> T {
+        self.recv_filtered(
+            &mut self.rng(),
+            |receiver| receiver.peek_matches(&pred),
+            |receiver| receiver.take_front_if(&pred),
+            |_, selected| selected.value,
+        )
+        .unwrap()
+    }
+
+    /// Like receive(), but passes the message together with the priority of the channel it came from to `f`
+    /// and returns the result. This allows branching on the priority without requiring `P: Clone`.
+    ///
+    /// `f` is called while holding the read lock of the multichannel, so it must not create, remove
+    /// or freeze channels. Keep it short, e.g. just pick a route and hand the message over.
+    pub fn receive_with<R>(&self, f: impl FnOnce(&P, T) -> R) -> R {
+        self.with_scratch(|scratch| {
+            self.recv_selected(&mut self.rng(), scratch, |state, selected| {
+                let (group_idx, _) = state.locate(selected.receiver.id).unwrap();
+                f(&state.groups[group_idx].priority, selected.value)
+            })
+        })
+        .unwrap()
+    }
+
+    /// Like receive(), but passes a reference to the message to `f` and drops the message afterwards,
+    /// for the common "inspect and discard" case. Named receive_ref(), as receive_with() passes the priority.
+    ///
+    /// crossbeam_channel can't lend out a buffered message, so the message is still moved out of the channel
+    /// once, but never to the caller. `f` runs after the lock of the multichannel was released, so it may use it.
+    pub fn receive_ref<R>(&self, f: impl FnOnce(&T) -> R) -> R {
+        let value = self.receive();
+        f(&value)
+    }
+
+    /// Spawn a thread that receives every message of this multichannel and forwards it into `target`,
+    /// at the priority `id_of` derives from the message. This chains multichannels into a pipeline.
+    ///
+    /// The bridge is a separate consumer of this multichannel, competing with the other consumers as usual.
+    /// For every derived priority, it creates an unfrozen, unbounded channel with the given weight in `target`
+    /// when it forwards the first message of that priority, and keeps using it afterwards.
+    ///
+    /// The thread ends, and the returned handle can be joined, once:
+    /// - recv() on this multichannel fails, usually because no channel is left, or
+    /// - a message can't be forwarded, because its channel in `target` was removed. The message is dropped.
+    ///
+    /// So to shut a bridge down, either remove all channels of this multichannel (or drop their senders),
+    /// or remove its channels from `target`, which it notices with the next message it forwards.
+    /// Create the channels of this multichannel before bridging, otherwise the bridge ends right away.
+    /// Dropping the bridge's senders when it ends closes its channels in `target`.
+    pub fn bridge_to(
+        &self,
+        target: &DynMultiReceiver<T, P, S>,
+        id_of: impl Fn(&T) -> P + Send + 'static,
+        weight: u32,
+    ) -> std::thread::JoinHandle<()>
+    where
+        T: Send + 'static,
+        P: Clone + Send + Sync + 'static,
+        S: Send + Sync + 'static,
+    {
+        assert!(weight > 0, "Weight must be greater than 0");
+        let source = self.clone();
+        let target = target.clone();
+        std::thread::spawn(move || {
+            let mut senders = std::collections::BTreeMap::new();
+            while let Ok(value) = source.recv() {
+                let priority = id_of(&value);
+                let sender = senders
+                    .entry(priority.clone())
+                    .or_insert_with(|| target.new_channel(priority, weight, false, None));
+                if sender.send(value).is_err() {
+                    return;
+                }
+            }
+        })
+    }
+
+    /// Receive up to `max_total` messages, grouped by the id of the channel they came from.
+    ///
+    /// Blocks until at least one message is available, then takes whatever else is deliverable
+    /// right now in the usual priority and weight order. Messages of the same channel keep their order.
+    /// Returns an empty map without blocking if `max_total` is 0.
+    pub fn receive_grouped(&self, max_total: usize) -> std::collections::HashMap<u32, Vec<T>> {
+        let mut grouped = std::collections::HashMap::<u32, Vec<T>>::new();
+        self.receive_batch(max_total, |id, value| {
+            grouped.entry(id).or_default().push(value)
+        });
+        grouped
+    }
+
+    /// Receive up to `max` messages, grouped by a key derived from their content with `key_fn`.
+    ///
+    /// Like receive_grouped(), this blocks until at least one message is available and then takes
+    /// whatever else is deliverable right now. Messages with the same key keep the order they were received in.
+    /// Returns an empty map without blocking if `max` is 0.
+    pub fn receive_group_by<K: Eq + std::hash::Hash>(
+        &self,
+        key_fn: impl Fn(&T) -> K,
+        max: usize,
+    ) -> std::collections::HashMap<K, Vec<T>> {
+        let mut grouped = std::collections::HashMap::<K, Vec<T>>::new();
+        self.receive_batch(max, |_, value| {
+            grouped.entry(key_fn(&value)).or_default().push(value)
+        });
+        grouped
+    }
+
+    /// Receive up to `max` messages into `out` and return how many were pushed, to take several messages per lock.
+    ///
+    /// Blocks until the first message is available, then takes whatever else is deliverable right now
+    /// in the usual priority and weight order, without waiting for more. Returns 0 without blocking if `max` is 0.
+    pub fn receive_many(&self, max: usize, out: &mut Vec<T>) -> usize {
+        let len = out.len();
+        self.receive_batch(max, |_, value| out.push(value));
+        out.len() - len
+    }
+
+    /// Receives up to `max_total` messages and passes them to `f` together with the id of their channel.
+    /// Blocks until at least one message is available, unless `max_total` is 0.
+    fn receive_batch(&self, max_total: usize, mut f: impl FnMut(u32, T)) {
+        if max_total == 0 {
+            return;
+        }
+        let mut rng = self.rng();
+        let (id, value) = self.recv_tagged(&mut rng).unwrap();
+        f(id, value);
+        let mut taken = Vec::new();
+        {
+            let state = self.state.read().unwrap();
+            while taken.len() + 1 < max_total {
+                match self.select(&state, &mut rng, |_| true).unwrap() {
+                    Selection::Found(selected) => {
+                        taken.push((selected.receiver.id, selected.value))
+                    }
+                    _ => break,
+                }
+            }
+        }
+        self.take_wakes(taken.len()).unwrap();
+        self.on_delivered(taken.len());
+        // Called without holding the lock, so `f` may do more work
+        for (id, value) in taken {
+            f(id, value);
+        }
+    }
+
+    /// Like receive(), but after selecting a channel, all messages currently buffered in that channel
+    /// are taken as well and folded into one value with `merge`, in the order they were sent.
+    ///
+    /// This is useful for messages where only the latest or a combined value matters, like position updates.
+    /// Messages of other channels are left untouched. `merge` is called without holding any lock.
+    pub fn receive_coalesced(&self, merge: impl Fn(T, T) -> T) -> T {
+        let mut values = SmallVec::<[T; 8]>::new();
+        self.with_scratch(|scratch| {
+            self.recv_selected(&mut self.rng(), scratch, |_, selected| {
+                values.push(selected.value);
+                // Only take what is buffered right now, a fast producer could keep us here forever
+                for _ in 0..selected.receiver.len() {
+                    match selected.receiver.try_recv() {
+                        Ok(value) => values.push(value),
+                        Err(_) => break,
+                    }
+                }
+                self.after_take(selected.receiver)
+            })
+        })
+        .and_then(|taken| taken)
+        .unwrap();
+        // The first message was already accounted for by recv_selected()
+        self.take_wakes(values.len() - 1).unwrap();
+        self.on_delivered(values.len() - 1);
+        values.into_iter().reduce(merge).unwrap()
+    }
+
+    /// Takes a message out of the highest priority group that has a deliverable one.
+    /// Only channels for which `filter` returns true are considered.
+    fn select<'a>(
+        &self,
+        state: &'a DynState<T, P, S>,
+        rng: &mut impl RngCore,
+        filter: impl Fn(&DynReceiver<T>) -> bool,
+    ) -> Result<Selection<'a, T>, RecvError> {
+        self.with_scratch(|scratch| {
+            self.select_with(state, rng, filter, DynReceiver::try_recv, scratch, None)
+        })
+    }
+
+    /// Like select(), but takes the message out of the selected channel with `take`
+    /// and keeps the candidates in the given scratch buffers.
+    /// A `policy_override` is used instead of size fairness and the configured policies.
+    fn select_with<'a>(
+        &self,
+        state: &'a DynState<T, P, S>,
+        rng: &mut impl RngCore,
+        filter: impl Fn(&DynReceiver<T>) -> bool,
+        take: impl Fn(&DynReceiver<T>) -> Result<T, crossbeam_channel::TryRecvError>,
+        scratch: &mut ReceiveScratch,
+        policy_override: Option<Policy>,
+    ) -> Result<Selection<'a, T>, RecvError> {
+        let size_fn = self.size_fn.as_ref().filter(|_| policy_override.is_none());
+        let receiver = |&(group_idx, inner_idx): &(usize, usize)| -> &'a DynReceiver<T> {
+            &state.groups[group_idx].receivers[inner_idx]
+        };
+        let promotions = state.promotions()?;
+        // Once aging is due, the highest group with a deliverable message is considered last
+        let aged = state.aging > 0 && state.aging_streak.load(Ordering::Relaxed) >= state.aging;
+        let deferred = match aged {
+            true => state
+                .ready_scan_order(&promotions)
+                .map(|(group_idx, _)| group_idx)
+                .find(|group_idx| state.has_deliverable(*group_idx, &promotions)),
+            false => None,
+        };
+        let scan_order = state
+            .ready_scan_order(&promotions)
+            .filter(|(group_idx, _)| Some(*group_idx) != deferred)
+            .chain(deferred.map(|group_idx| (group_idx, &state.groups[group_idx])));
+        let mut rendezvous = false;
+        let ReceiveScratch {
+            candidates,
+            ordered,
+        } = scratch;
+        for (group_idx, group) in scan_order {
+            let policy = policy_override.or(group.policy).unwrap_or(self.policy);
+            candidates.clear();
+            group.ready_receivers(group_idx, state.freezing, candidates)?;
+            if !promotions.is_empty() {
+                // Promoted channels are only considered in the group they were promoted to
+                candidates.retain(|pos| promotions.iter().all(|(id, _)| *id != receiver(pos).id));
+                let promoted = promotions
+                    .iter()
+                    .filter(|(_, idx)| *idx == group_idx)
+                    .filter_map(|(id, _)| state.locate(*id))
+                    .filter(|pos| {
+                        let r = receiver(pos);
+                        !r.is_frozen() && r.has_message()
+                    });
+                candidates.extend(promoted);
+            }
+            candidates.retain(|pos| filter(receiver(pos)));
+            // With every channel of the group deliverable, the first weighted pick samples from the cached
+            // weights of the group in log n, instead of summing up and scanning all candidates
+            let table = (size_fn.is_none()
+                && policy == Policy::Weighted
+                && promotions.is_empty()
+                && candidates.len() == group.receivers.len())
+            .then(|| {
+                candidates.clear();
+                candidates
+                    .extend((0..group.receivers.len()).map(|inner_idx| (group_idx, inner_idx)));
+                group.cumulative_weights()
+            });
+            // Summed up as u64, so even billions of channels with a weight of u32::MAX can't overflow
+            let mut total_weight: u64 = match table {
+                Some(table) => table.last().copied().unwrap_or(0),
+                None => candidates
+                    .iter()
+                    .map(|pos| receiver(pos).weight as u64)
+                    .sum(),
+            };
+            // Once a candidate turned out to be empty, the rest are sorted so the next pick comes last,
+            // keeping the worst case at n log n instead of scanning all candidates for every pick
+            let mut in_order = false;
+            while !candidates.is_empty() {
+                // Weights are checked to be positive, but if only zero weights are left, serve the candidates in turn
+                let policy = match policy {
+                    Policy::Weighted | Policy::DeterministicWeighted if total_weight == 0 => {
+                        Policy::RoundRobin
+                    }
+                    policy => policy,
+                };
+                let candidate_index = match (size_fn, policy) {
+                    _ if in_order => candidates.len() - 1,
+                    (Some(_), _) => fairest_candidate(candidates.iter().map(receiver)),
+                    (None, Policy::RoundRobin) => loop {
+                        let cursor = group.cursor.load(Ordering::Relaxed);
+                        let candidate_index =
+                            round_robin_candidate(candidates.iter().map(receiver), cursor);
+                        let next = receiver(&candidates[candidate_index]).id.wrapping_add(1);
+                        // Claim the turn before taking, so concurrent consumers move on to the next channels
+                        // instead of serving the same one
+                        if group
+                            .cursor
+                            .compare_exchange(cursor, next, Ordering::Relaxed, Ordering::Relaxed)
+                            .is_ok()
+                        {
+                            break candidate_index;
+                        }
+                    },
+                    (None, Policy::Weighted) => match table {
+                        Some(table) => table_candidate(table, rng)?,
+                        None => {
+                            weighted_candidate(candidates.iter().map(receiver), total_weight, rng)?
+                        }
+                    },
+                    (None, Policy::DeterministicWeighted) => {
+                        deficit_candidate(candidates.iter().map(receiver))
+                    }
+                };
+                let selected = receiver(&candidates[candidate_index]);
+                rendezvous |= selected.inner.capacity() == Some(0);
+                match take(selected) {
+                    Ok(value) => {
+                        match (size_fn, policy) {
+                            (Some(size_fn), _) => group.charge(selected, size_fn(&value)),
+                            (None, Policy::DeterministicWeighted) => {
+                                settle_deficits(candidates.iter().map(receiver), selected)
+                            }
+                            // Otherwise the turn was already claimed when picking
+                            (None, Policy::RoundRobin) if in_order => group
+                                .cursor
+                                .store(selected.id.wrapping_add(1), Ordering::Relaxed),
+                            (None, Policy::RoundRobin) => {}
+                            (None, Policy::Weighted) => {}
+                        }
+                        if self.track_starvation {
+                            for (i, sibling) in candidates.iter().enumerate() {
+                                if i != candidate_index {
+                                    receiver(sibling)
+                                        .passed_over
+                                        .fetch_add(1, Ordering::Relaxed);
+                                }
+                            }
+                        }
+                        if aged {
+                            state.aging_streak.store(0, Ordering::Relaxed);
+                        } else if state.aging > 0 {
+                            state.aging_streak.fetch_add(1, Ordering::Relaxed);
+                        }
+                        self.after_take(selected)?;
+                        return Ok(Selection::Found(Selected {
+                            receiver: selected,
+                            value,
+                        }));
+                    }
+                    Err(crossbeam_channel::TryRecvError::Empty) => {}
+                    Err(crossbeam_channel::TryRecvError::Disconnected) => {
+                        self.schedule_cleanup(selected.id)?;
+                    }
+                };
+                total_weight -= selected.weight as u64;
+                candidates.swap_remove(candidate_index);
+                if !in_order {
+                    in_order = true;
+                    let cursor = group.cursor.load(Ordering::Relaxed);
+                    ordered.clear();
+                    ordered.extend(candidates.iter().map(|pos| {
+                        let key =
+                            pick_order_key(receiver(pos), size_fn.is_some(), policy, cursor, rng);
+                        (key, *pos)
+                    }));
+                    ordered.sort_unstable_by_key(|(key, _)| *key);
+                    candidates.clear();
+                    candidates.extend(ordered.iter().map(|(_, pos)| *pos));
+                }
+            }
+            // The message about to be handed over has priority over all lower groups
+            if rendezvous {
+                break;
+            }
+        }
+        Ok(if rendezvous {
+            Selection::Rendezvous
+        } else {
+            Selection::Empty
+        })
+    }
+
+    /// Must be called after a message was taken out of a receiver, while still holding the state lock.
+    fn after_take(&self, receiver: &DynReceiver<T>) -> Result<(), RecvError> {
+        if receiver.is_finished() {
+            self.schedule_cleanup(receiver.id)?;
+        }
+        Ok(())
+    }
+
+    /// Must be called after messages were delivered, once the state lock is released.
+    fn on_delivered(&self, amount: usize) {
+        self.total_received
+            .fetch_add(amount as u64, Ordering::Relaxed);
+        self.backlog.sub(amount);
+        // Finished limited channels are removed right away
+        let _ = self.run_cleanup();
+    }
+
+    /// Receive the globally oldest buffered message, ignoring priorities and weights entirely.
+    ///
+    /// Messages are ordered by the time they were sent, across all channels, so a consumer using
+    /// only this method observes strict FIFO order. Frozen channels are still not considered.
+    /// This has to look at the head of every channel, so it is always O(n) in the amount of channels.
+    pub fn receive_oldest(&self) -> T {
+        self.run_cleanup().unwrap();
+        let mut woken = false;
+        let value = loop {
+            if let Some(value) = self.select_oldest(&self.state.read().unwrap()).unwrap() {
+                break value;
+            }
+            // Nothing deliverable, wait for the next send or unfreeze instead of spinning
+            self.wait_for_wake().unwrap();
+            woken = true;
+        };
+        if !woken {
+            self.take_wakes(1).unwrap();
+        }
+        self.on_delivered(1);
+        value
+    }
+
+    /// Takes the oldest deliverable message, or returns None if there is none.
+    fn select_oldest(&self, state: &DynState<T, P, S>) -> Result<Option<T>, RecvError> {
+        loop {
+            let mut oldest: Option<(u64, &DynReceiver<T>)> = None;
+            for receiver in state.groups.iter().flat_map(|g| &g.receivers) {
+                if receiver.is_frozen() {
+                    continue;
+                }
+                match receiver.peek_seq() {
+                    Ok(seq) => {
+                        if oldest.is_none_or(|(oldest_seq, _)| seq < oldest_seq) {
+                            oldest = Some((seq, receiver));
+                        }
+                    }
+                    Err(crossbeam_channel::TryRecvError::Empty) => {}
+                    Err(crossbeam_channel::TryRecvError::Disconnected) => {
+                        self.schedule_cleanup(receiver.id)?;
+                    }
+                }
+            }
+            // Another consumer may have taken the head in the meantime, in which case we rescan
+            let Some((seq, receiver)) = oldest else {
+                return Ok(None);
+            };
+            if let Some(value) = receiver.take_front(seq) {
+                self.after_take(receiver)?;
+                return Ok(Some(value));
+            }
+        }
+    }
+
+    /// Register a callback that is called whenever the total amount of buffered messages
+    /// across all channels rises above `threshold`.
+    ///
+    /// The callback is edge triggered: it fires once per crossing, not on every send while the
+    /// backlog stays above the threshold. It is called on the thread whose send caused the crossing,
+    /// without holding any lock of the multichannel, so it may use the multichannel itself.
+    pub fn on_high_watermark(&self, threshold: usize, f: impl Fn() + Send + Sync + 'static) {
+        self.add_watermark(threshold, true, Arc::new(f));
+    }
+
+    /// Register a callback that is called whenever the total amount of buffered messages
+    /// across all channels falls below `threshold`.
+    ///
+    /// Like on_high_watermark(), this is edge triggered. Messages dropped together with a removed
+    /// channel also count as leaving the backlog.
+    pub fn on_low_watermark(&self, threshold: usize, f: impl Fn() + Send + Sync + 'static) {
+        self.add_watermark(threshold, false, Arc::new(f));
+    }
+
+    fn add_watermark(&self, threshold: usize, rising: bool, callback: WatermarkFn) {
+        let mut watermarks = self.backlog.watermarks.write().unwrap();
+        watermarks.push(Watermark {
+            threshold,
+            rising,
+            callback,
+        });
+        self.backlog.armed.store(true, Ordering::Relaxed);
+    }
+
+    /// Make receive() consider the channels with the given priority before all others,
+    /// until unpin_tier() is called. This is useful to quickly drain the backlog of one tier.
+    ///
+    /// The pin also applies to channels of this priority created after pinning.
+    /// Pinning another priority replaces the previous pin.
+    pub fn pin_tier_first(&self, priority: P) {
+        self.state.write().unwrap().pinned = Some(priority);
+    }
+
+    /// Restore the normal priority order after pin_tier_first().
+    pub fn unpin_tier(&self) {
+        self.state.write().unwrap().pinned = None;
+    }
+
+    /// Keep busy high priority channels from starving the lower ones: after `threshold` receives,
+    /// the next receive prefers the highest but one tier with a deliverable message, if there is one.
+    /// So with a threshold of 3, a saturated tier yields every fourth receive to the next lower tier with messages.
+    ///
+    /// The receives are counted across all clones of this multireceiver. A threshold of 0 disables aging, the default.
+    pub fn set_aging(&self, threshold: u32) {
+        let mut state = self.state.write().unwrap();
+        state.aging = threshold;
+        *state.aging_streak.get_mut() = 0;
+    }
+
+    /// Set how receive() picks between the channels of the given priority, instead of the policy set with with_policy().
+    ///
+    /// The policy sticks with the priority, also while it has no channels, and is remapped by remap_priorities().
+    /// Size fairness set with with_size_fairness() still takes precedence.
+    pub fn set_group_policy(&self, priority: P, policy: Policy) {
+        let mut state = self.state.write().unwrap();
+        if let Ok(idx) = state.groups.binary_search_by(|g| g.priority.cmp(&priority)) {
+            state.groups[idx].policy = Some(policy);
+        }
+        state.group_policies.insert(priority, policy);
+    }
+
+    /// Link two channels, so the channel `to_id` inherits the priority of the channel `from_id`
+    /// while `from_id` has buffered messages. This schedules dependent work alongside its prerequisite.
+    ///
+    /// - The priority is only ever raised: if `from_id` has a lower priority than `to_id`, the link has no effect.
+    /// - A promoted channel competes by its own weight with the channels of the inherited priority,
+    ///   and is not considered in its own priority group in the meantime.
+    /// - Messages buffered in a frozen `from_id` still count as a backlog.
+    /// - Links are transitive: if `from_id` is promoted itself, `to_id` inherits the promoted priority.
+    ///   A channel may have several prerequisites, it then inherits the highest of their priorities.
+    /// - Links that would form a cycle are rejected with LinkError::Cycle, this includes linking a channel to itself.
+    /// - Links are dropped together with either channel.
+    pub fn link_priority(&self, from_id: u32, to_id: u32) -> Result<(), LinkError> {
+        let mut state = self.state.write().unwrap();
+        for id in [from_id, to_id] {
+            if !state.lookup.contains_key(&id) {
+                return Err(LinkError::UnknownChannel(id));
+            }
+        }
+        if from_id == to_id || state.depends_on(from_id, to_id) {
+            return Err(LinkError::Cycle);
+        }
+        let prerequisites = state.links.entry(to_id).or_default();
+        if !prerequisites.contains(&from_id) {
+            prerequisites.push(from_id);
+        }
+        Ok(())
+    }
+
+    /// Remove a link created with link_priority(). Returns false if there was no such link.
+    pub fn unlink_priority(&self, from_id: u32, to_id: u32) -> bool {
+        let mut state = self.state.write().unwrap();
+        let Some(prerequisites) = state.links.get_mut(&to_id) else {
+            return false;
+        };
+        let len = prerequisites.len();
+        prerequisites.retain(|from| *from != from_id);
+        let removed = prerequisites.len() != len;
+        if prerequisites.is_empty() {
+            state.links.remove(&to_id);
+        }
+        removed
+    }
+
+    /// Returns (id, weight, capacity) of every channel with the given priority.
+    /// The capacity is None for unbounded channels. If no channel has this priority, the result is empty.
+    pub fn channels_at_priority(&self, priority: &P) -> Vec<(u32, u32, Option<usize>)> {
+        let state = self.state.read().unwrap();
+        match state.group(priority) {
+            Some(group) => group
+                .receivers
+                .iter()
+                .map(|r| (r.id, r.weight, r.inner.capacity()))
+                .collect(),
+            None => Vec::new(),
+        }
+    }
+
+    /// Returns the metadata of every channel, in the order receive() considers their tiers.
+    ///
+    /// This is a snapshot for introspection: channels may be created, changed or removed right after.
+    pub fn channels(&self) -> Vec<ChannelInfo<P>>
+    where
+        P: Clone,
+    {
+        let state = self.state.read().unwrap();
+        state
+            .groups
+            .iter()
+            .flat_map(|group| {
+                group.receivers.iter().map(|r| ChannelInfo {
+                    id: r.id,
+                    priority: group.priority.clone(),
+                    weight: r.weight,
+                    frozen: r.is_frozen(),
+                    queued: r.len(),
+                })
+            })
+            .collect()
+    }
+
+    /// Returns the message counters of the channel with the given id, or None if it doesn't exist.
+    pub fn stats(&self, id: u32) -> Option<ChannelStats> {
+        let state = self.state.read().unwrap();
+        let receiver = state.receiver(id)?;
+        Some(ChannelStats {
+            sent: receiver.activity.sent.load(Ordering::Relaxed),
+            received: receiver.activity.received.load(Ordering::Relaxed),
+            current_len: receiver.len(),
+        })
+    }
+
+    /// Freeze or unfreeze all channels with the given ids under a single lock.
+    /// Ids of channels that no longer exist are skipped.
+    pub fn set_frozen_many(&self, ids: impl IntoIterator<Item = u32>, frozen: bool) {
+        let mut thawed = 0;
+        {
+            let state = self.state.read().unwrap();
+            state.check_freezing(frozen);
+            for id in ids {
+                thawed += state.try_set_frozen(id, frozen).unwrap_or(0);
+            }
+        }
+        wake_all(&self.condvar, thawed);
+    }
+
+    /// Freeze or unfreeze all channels with the given priority under a single lock.
+    /// Does nothing if no channel has this priority.
+    pub fn set_group_frozen(&self, priority: &P, frozen: bool) {
+        let mut thawed = 0;
+        {
+            let state = self.state.read().unwrap();
+            state.check_freezing(frozen);
+            if let Ok(idx) = state.groups.binary_search_by(|g| g.priority.cmp(priority)) {
+                for receiver in &state.groups[idx].receivers {
+                    thawed += state.try_set_frozen(receiver.id, frozen).unwrap_or(0);
+                }
+            }
+        }
+        wake_all(&self.condvar, thawed);
+    }
+
+    /// Freeze all channels under a single lock, e.g. to pause delivery for maintenance.
+    /// receive() blocks until a channel is unfrozen again. Channels created afterwards are not frozen.
+    pub fn freeze_all(&self) {
+        self.set_all_frozen(true);
+    }
+
+    /// Unfreeze all channels under a single lock, waking up receivers for their buffered messages.
+    pub fn unfreeze_all(&self) {
+        self.set_all_frozen(false);
+    }
+
+    fn set_all_frozen(&self, frozen: bool) {
+        let mut thawed = 0;
+        {
+            let state = self.state.read().unwrap();
+            state.check_freezing(frozen);
+            for &id in state.lookup.keys() {
+                thawed += state.try_set_frozen(id, frozen).unwrap_or(0);
+            }
+        }
+        wake_all(&self.condvar, thawed);
+    }
+
+    /// Capture the current frozen state of every channel.
+    ///
+    /// The snapshot can later be passed to restore_freeze() to return every channel
+    /// to exactly the frozen state it had at the time of the snapshot.
+    pub fn freeze_snapshot(&self) -> FrozenStates {
+        let state = self.state.read().unwrap();
+        let mut states = HashMap::default();
+        for group in &state.groups {
+            for receiver in &group.receivers {
+                states.insert(receiver.id, receiver.is_frozen());
+            }
+        }
+        FrozenStates { states }
+    }
+
+    /// Restore the frozen states captured by freeze_snapshot().
+    ///
+    /// Channels that were removed since the snapshot was taken are skipped.
+    /// Channels created after the snapshot was taken are left untouched.
+    pub fn restore_freeze(&self, states: &FrozenStates) {
+        let mut thawed = 0;
+        {
+            let state = self.state.read().unwrap();
+            state.check_freezing(states.iter().any(|(_, frozen)| frozen));
+            for (id, frozen) in states.iter() {
+                thawed += state.try_set_frozen(id, frozen).unwrap_or(0);
+            }
+        }
+        wake_all(&self.condvar, thawed);
+    }
+
+    /// Returns the total amount of messages delivered by receive() since this multichannel was created.
+    ///
+    /// This counts deliveries, not sends: messages that are still buffered or that were dropped
+    /// together with a removed channel are not included. The counter is shared by all clones
+    /// of this DynMultiReceiver.
+    pub fn total_received(&self) -> u64 {
+        self.total_received.load(Ordering::Relaxed)
+    }
+
+    /// Returns the current value of the internal wake counter, which is increased by every send
+    /// and by unfreezing channels with buffered messages, and decreased by every receive.
+    ///
+    /// This is a momentary snapshot meant for debugging the wake accounting, it may be outdated
+    /// by the time it is returned.
+    pub fn pending_wakes(&self) -> usize {
+        self.condvar.0.lock().unwrap().pending
+    }
+
+    /// Returns the channel count, group count, pending messages per priority, total received messages
+    /// and frozen channel count in the Prometheus text exposition format, e.g. for a scrape endpoint.
+    ///
+    /// All values are taken under a single read lock, so they form a consistent snapshot.
+    /// Priorities are used as label values through their Debug representation.
+    #[cfg(feature = "metrics")]
+    pub fn metrics_text(&self) -> String
+    where
+        P: std::fmt::Debug,
+    {
+        use std::fmt::Write;
+
+        fn header(out: &mut String, name: &str, kind: &str, help: &str) {
+            writeln!(out, "# HELP multichannel_{} {}", name, help).unwrap();
+            writeln!(out, "# TYPE multichannel_{} {}", name, kind).unwrap();
+        }
+
+        let state = self.state.read().unwrap();
+        let receivers = || state.groups.iter().flat_map(|g| &g.receivers);
+        let mut out = String::new();
+
+        header(
+            &mut out,
+            "channels",
+            "gauge",
+            "Amount of registered channels.",
+        );
+        writeln!(out, "multichannel_channels {}", receivers().count()).unwrap();
+        header(&mut out, "groups", "gauge", "Amount of priority groups.");
+        writeln!(out, "multichannel_groups {}", state.groups.len()).unwrap();
+        header(
+            &mut out,
+            "pending",
+            "gauge",
+            "Buffered messages per priority.",
+        );
+        for group in &state.groups {
+            let priority = format!("{:?}", group.priority)
+                .replace('\\', "\\\\")
+                .replace('"', "\\\"")
+                .replace('\n', "\\n");
+            let pending: usize = group.receivers.iter().map(|r| r.len()).sum();
+            writeln!(
+                out,
+                "multichannel_pending{{priority=\"{}\"}} {}",
+                priority, pending
+            )
+            .unwrap();
+        }
+        header(
+            &mut out,
+            "received_total",
+            "counter",
+            "Messages delivered by receive().",
+        );
+        writeln!(out, "multichannel_received_total {}", self.total_received()).unwrap();
+        header(
+            &mut out,
+            "frozen_channels",
+            "gauge",
+            "Amount of frozen channels.",
+        );
+        let frozen = receivers().filter(|r| r.is_frozen()).count();
+        writeln!(out, "multichannel_frozen_channels {}", frozen).unwrap();
+        out
+    }
+
+    /// Returns (id, passed over count) of every channel, sorted by id.
+    ///
+    /// The count is the amount of receive() calls that picked another channel of the same priority
+    /// while this channel had a deliverable message. A count that is high relative to the deliveries
+    /// of the channel indicates that its weight starves it. All counts are 0 unless the multichannel
+    /// was created with with_starvation_tracking().
+    pub fn starvation_report(&self) -> Vec<(u32, u64)> {
+        let state = self.state.read().unwrap();
+        let mut report: Vec<_> = state
+            .groups
+            .iter()
+            .flat_map(|g| &g.receivers)
+            .map(|r| (r.id, r.passed_over.load(Ordering::Relaxed)))
+            .collect();
+        report.sort_unstable();
+        report
+    }
+
+    /// Returns the amount of messages buffered in all channels, including frozen ones.
+    pub fn len(&self) -> usize {
+        let state = self.state.read().unwrap();
+        state
+            .groups
+            .iter()
+            .flat_map(|g| &g.receivers)
+            .map(|r| r.len())
+            .sum()
+    }
+
+    /// Returns the amount of messages buffered in the channels with the given priority, including frozen ones,
+    /// or 0 if no channel has this priority. Handy to shed load at a single tier.
+    pub fn group_len(&self, priority: &P) -> usize {
+        let state = self.state.read().unwrap();
+        state
+            .group(priority)
+            .map_or(0, |group| group.receivers.iter().map(|r| r.len()).sum())
+    }
+
+    /// Returns true if no channel holds a message. Unlike no_channels(), this ignores empty channels.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn no_channels(&self) -> bool {
+        self.state.read().unwrap().is_empty()
+    }
+
+    /// Block until there is at least one channel, e.g. to confirm that the producers were set up.
+    /// Returns right away if there already is one.
+    pub fn wait_for_channel(&self) {
+        let (lock, condvar) = &*self.channel_added;
+        let mut guard = lock.lock().unwrap();
+        while self.no_channels() {
+            guard = condvar.wait(guard).unwrap();
+        }
+    }
+
+    /// Returns the amount of live clones of this multireceiver, including this one.
+    /// Once all of them are dropped, sends fail with SendError::Disconnected.
+    pub fn receiver_count(&self) -> usize {
+        self.amount_multireceivers.load(Ordering::Relaxed)
     }
 }
@@ -0,0 +1,121 @@
+//! The locks used by the multichannel: the ones from std by default, or the smaller parking_lot locks
+//! with the parking-lot feature.
+//!
+//! The parking_lot locks are wrapped to offer the same methods as the std ones, so the rest of the crate
+//! doesn't care which backend it runs on. They never poison, so locking them always succeeds.
+
+use std::sync::{LockResult, PoisonError};
+
+#[cfg(not(feature = "parking-lot"))]
+pub(crate) use std::sync::{Condvar, Mutex, MutexGuard, RwLock};
+
+#[cfg(feature = "parking-lot")]
+pub(crate) use parking_lot_backend::{Condvar, Mutex, MutexGuard, RwLock};
+
+/// Takes the guard of a poisoned lock anyway, for locks whose data a panic can't leave half updated.
+pub(crate) fn recover<G>(result: LockResult<G>) -> G {
+    result.unwrap_or_else(PoisonError::into_inner)
+}
+
+#[cfg(feature = "parking-lot")]
+mod parking_lot_backend {
+    use std::{
+        ops::{Deref, DerefMut},
+        sync::{LockResult, TryLockError, TryLockResult},
+        time::Duration,
+    };
+
+    pub(crate) struct Mutex<T: ?Sized>(parking_lot::Mutex<T>);
+
+    impl<T> Mutex<T> {
+        pub(crate) fn new(value: T) -> Self {
+            Self(parking_lot::Mutex::new(value))
+        }
+    }
+
+    impl<T: ?Sized> Mutex<T> {
+        pub(crate) fn lock(&self) -> LockResult<MutexGuard<'_, T>> {
+            Ok(MutexGuard(self.0.lock()))
+        }
+
+        pub(crate) fn try_lock(&self) -> TryLockResult<MutexGuard<'_, T>> {
+            self.0
+                .try_lock()
+                .map(MutexGuard)
+                .ok_or(TryLockError::WouldBlock)
+        }
+    }
+
+    /// Wrapped, as Condvar::wait() takes the guard by value like the std one.
+    pub(crate) struct MutexGuard<'a, T: ?Sized>(parking_lot::MutexGuard<'a, T>);
+
+    impl<T: ?Sized> Deref for MutexGuard<'_, T> {
+        type Target = T;
+
+        fn deref(&self) -> &T {
+            &self.0
+        }
+    }
+
+    impl<T: ?Sized> DerefMut for MutexGuard<'_, T> {
+        fn deref_mut(&mut self) -> &mut T {
+            &mut self.0
+        }
+    }
+
+    pub(crate) struct RwLock<T: ?Sized>(parking_lot::RwLock<T>);
+
+    impl<T> RwLock<T> {
+        pub(crate) fn new(value: T) -> Self {
+            Self(parking_lot::RwLock::new(value))
+        }
+    }
+
+    impl<T: ?Sized> RwLock<T> {
+        pub(crate) fn read(&self) -> LockResult<parking_lot::RwLockReadGuard<'_, T>> {
+            Ok(self.0.read())
+        }
+
+        pub(crate) fn write(&self) -> LockResult<parking_lot::RwLockWriteGuard<'_, T>> {
+            Ok(self.0.write())
+        }
+
+        pub(crate) fn try_write(&self) -> TryLockResult<parking_lot::RwLockWriteGuard<'_, T>> {
+            self.0.try_write().ok_or(TryLockError::WouldBlock)
+        }
+    }
+
+    pub(crate) struct Condvar(parking_lot::Condvar);
+
+    impl Condvar {
+        pub(crate) fn new() -> Self {
+            Self(parking_lot::Condvar::new())
+        }
+
+        pub(crate) fn notify_one(&self) {
+            self.0.notify_one();
+        }
+
+        pub(crate) fn notify_all(&self) {
+            self.0.notify_all();
+        }
+
+        pub(crate) fn wait<'a, T>(
+            &self,
+            mut guard: MutexGuard<'a, T>,
+        ) -> LockResult<MutexGuard<'a, T>> {
+            self.0.wait(&mut guard.0);
+            Ok(guard)
+        }
+
+        /// Like the std one, but returns whether the wait timed out as a plain bool.
+        pub(crate) fn wait_timeout<'a, T>(
+            &self,
+            mut guard: MutexGuard<'a, T>,
+            timeout: Duration,
+        ) -> LockResult<(MutexGuard<'a, T>, bool)> {
+            let timed_out = self.0.wait_for(&mut guard.0, timeout).timed_out();
+            Ok((guard, timed_out))
+        }
+    }
+}
@@ -1,12 +1,20 @@
 #[cfg(test)]
+#[allow(clippy::module_inception)]
 mod tests {
     use std::{
-        sync::{Arc, Barrier},
-        time::Duration,
+        sync::{
+            atomic::{AtomicUsize, Ordering},
+            Arc, Barrier,
+        },
+        time::{Duration, Instant},
     };
 
-    use crate::DynMultiReceiver;
-    use rand::{prelude::SliceRandom, thread_rng, Rng};
+    use crate::{
+        ChannelEvent, ChannelInfo, ChannelStats, DynMultiReceiver, EmptyBehavior, LinkError,
+        NameTakenError, Policy, ReceiveScratch, RecvError, RecvTimeoutError, Selection,
+        SendBackoff, SendError, SendTimeoutError, TryReceiveError, TryRecvFromError, TrySendError,
+    };
+    use rand::{prelude::SliceRandom, rngs::SmallRng, thread_rng, Rng, SeedableRng};
 
     #[test]
     fn creation_destruction() {
@@ -22,6 +30,17 @@ mod tests {
         }
     }
 
+    #[test]
+    fn remove_whole_group() {
+        let mrx = DynMultiReceiver::<i32, u16>::new();
+        let high = mrx.new_channel(1, 10, false, None);
+        let low = mrx.new_channel(2, 10, false, None);
+        mrx.remove_channel(&high);
+        low.set_frozen(true);
+        mrx.remove_channel(&low);
+        assert!(mrx.no_channels());
+    }
+
     #[test]
     fn parallel_creation_destruction() {
         let amount = 256;
@@ -91,12 +110,107 @@ mod tests {
             sender_high_prio.send(0).unwrap();
         });
 
-        // Let's hope the threads have enough time to send the messages
-        std::thread::sleep(Duration::from_millis(20));
+        // Wait until both senders are waiting for the rendezvous
+        while mrx.receivable_priorities() != vec![1, 10] {
+            std::thread::yield_now();
+        }
         assert_eq!(mrx.receive(), 0);
         assert_eq!(mrx.receive(), 1);
     }
 
+    #[test]
+    fn competing_bounded_0() {
+        let mrx = DynMultiReceiver::<u16, u16>::new();
+        let send = |sender: &Arc<crate::DynSender<u16, u16>>, priority| {
+            let sender = sender.clone();
+            std::thread::spawn(move || sender.send(priority).unwrap())
+        };
+        let senders: Vec<_> = (0..3u16)
+            .map(|priority| Arc::new(mrx.new_channel(priority, 1, false, Some(0))))
+            .collect();
+        let mut remaining = [10; 3];
+        let mut sending: Vec<_> = (0..3u16)
+            .map(|priority| Some(send(&senders[priority as usize], priority)))
+            .collect();
+        for _ in 0..30 {
+            let waiting: Vec<u16> = (0..3).filter(|p| remaining[*p as usize] > 0).collect();
+            while mrx.receivable_priorities() != waiting {
+                std::thread::yield_now();
+            }
+            // Every sender with messages left is waiting, so the highest priority one must win
+            let received = mrx.receive();
+            assert_eq!(received, waiting[0]);
+            let idx = received as usize;
+            sending[idx].take().unwrap().join().unwrap();
+            remaining[idx] -= 1;
+            if remaining[idx] > 0 {
+                sending[idx] = Some(send(&senders[idx], received));
+            }
+        }
+        assert_eq!(mrx.try_receive(), Err(TryReceiveError::Empty));
+    }
+
+    #[test]
+    fn reserve_capacity() {
+        let mrx = DynMultiReceiver::<i32, u16>::new();
+        let sender = Arc::new(mrx.new_channel_with_reserve(10, 10, false, 3, 2));
+        for x in 0..3 {
+            sender.send(x).unwrap();
+        }
+
+        // The main capacity is used up, so a normal send has to wait
+        let blocked_sender = sender.clone();
+        let blocked = std::thread::spawn(move || blocked_sender.send(100).unwrap());
+        std::thread::sleep(Duration::from_millis(50));
+        assert!(!blocked.is_finished());
+
+        // Urgent sends can still use the reserve
+        sender.send_urgent(3).unwrap();
+        sender.send_urgent(4).unwrap();
+        std::thread::sleep(Duration::from_millis(50));
+        assert!(!blocked.is_finished());
+
+        for x in 0..3 {
+            assert_eq!(mrx.receive(), x);
+        }
+        blocked.join().unwrap();
+        for x in [3, 4, 100] {
+            assert_eq!(mrx.receive(), x);
+        }
+    }
+
+    #[test]
+    fn reserve_capacity_remove_unblocks() {
+        let mrx = DynMultiReceiver::<i32, u16>::new();
+        let sender = Arc::new(mrx.new_channel_with_reserve(10, 10, false, 1, 1));
+        sender.send(0).unwrap();
+        let blocked_sender = sender.clone();
+        let blocked = std::thread::spawn(move || blocked_sender.send(1));
+        std::thread::sleep(Duration::from_millis(20));
+        mrx.remove_channel(&sender);
+        assert!(blocked.join().unwrap().is_err());
+    }
+
+    #[test]
+    fn send_backoff_spins_before_parking() {
+        // A zero timeout gives up as soon as the sender would park, so only spinning can deliver the message
+        for (backoff, delivered) in [
+            (SendBackoff::Park, false),
+            (SendBackoff::SpinThenPark { spins: u32::MAX }, true),
+        ] {
+            let mrx = Arc::new(DynMultiReceiver::<i32, u16>::new().with_send_backoff(backoff));
+            let sender = mrx.new_channel(10, 10, false, Some(1));
+            sender.send(0).unwrap();
+            let receiving = mrx.clone();
+            let receiver = std::thread::spawn(move || {
+                std::thread::sleep(Duration::from_millis(20));
+                receiving.receive()
+            });
+            assert_eq!(sender.send_timeout(1, Duration::ZERO).is_ok(), delivered);
+            assert_eq!(receiver.join().unwrap(), 0);
+        }
+    }
+
     #[test]
     fn send_recv_unbounded_chaotic() {
         let amount_senders = 500;
@@ -210,6 +324,382 @@ mod tests {
         }
     }
 
+    #[test]
+    fn receive_oldest() {
+        let mrx = DynMultiReceiver::<i32, u16>::new();
+        let senders = [
+            mrx.new_channel(1, 10, false, None),
+            mrx.new_channel(5, 1, false, None),
+            mrx.new_channel(5, 100, false, Some(1000)),
+            mrx.new_channel(9, 10, false, None),
+        ];
+        for x in 0..1000 {
+            senders[thread_rng().gen_range(0..senders.len())]
+                .send(x)
+                .unwrap();
+        }
+        for x in 0..1000 {
+            assert_eq!(mrx.receive_oldest(), x);
+        }
+    }
+
+    #[test]
+    fn receive_oldest_mixed_with_receive() {
+        let mrx = DynMultiReceiver::<i32, u16>::new();
+        let low = mrx.new_channel(5, 10, false, None);
+        let high = mrx.new_channel(1, 10, false, None);
+        low.send(0).unwrap();
+        low.send(1).unwrap();
+        high.send(2).unwrap();
+        high.send(3).unwrap();
+        assert_eq!(mrx.receive_oldest(), 0);
+        assert_eq!(mrx.receive(), 2);
+        // The head of the high channel was peeked and must still come before the rest of it
+        assert_eq!(mrx.receive(), 3);
+        assert_eq!(mrx.receive(), 1);
+    }
+
+    #[test]
+    fn size_fairness() {
+        let mrx = DynMultiReceiver::<(u8, u64), u16>::new().with_size_fairness(|msg| msg.1);
+        let large = mrx.new_channel(10, 10, false, None);
+        let small = mrx.new_channel(10, 10, false, None);
+        let heavy = mrx.new_channel(10, 20, false, None);
+        for _ in 0..1000 {
+            large.send((0, 10)).unwrap();
+            small.send((1, 1)).unwrap();
+            heavy.send((2, 1)).unwrap();
+        }
+
+        let mut bytes = [0; 3];
+        let mut counts = [0; 3];
+        for _ in 0..1000 {
+            let (channel, size) = mrx.receive();
+            bytes[channel as usize] += size;
+            counts[channel as usize] += 1;
+        }
+        // Bytes are balanced by weight, so the large channel is selected far less often
+        assert!(counts[0] * 5 < counts[1]);
+        assert!(bytes[0].abs_diff(bytes[1]) <= 10);
+        assert!(bytes[2].abs_diff(2 * bytes[1]) <= 10);
+    }
+
+    #[test]
+    fn watermarks() {
+        let mrx = DynMultiReceiver::<i32, u16>::new();
+        let high = Arc::new(AtomicUsize::new(0));
+        let low = Arc::new(AtomicUsize::new(0));
+        let high_clone = high.clone();
+        mrx.on_high_watermark(5, move || {
+            high_clone.fetch_add(1, Ordering::Relaxed);
+        });
+        let low_clone = low.clone();
+        mrx.on_low_watermark(2, move || {
+            low_clone.fetch_add(1, Ordering::Relaxed);
+        });
+        let count = |counter: &Arc<AtomicUsize>| counter.load(Ordering::Relaxed);
+
+        let sender = mrx.new_channel(10, 10, false, None);
+        for x in 0..5 {
+            sender.send(x).unwrap();
+        }
+        assert_eq!((count(&high), count(&low)), (0, 0));
+        sender.send(5).unwrap();
+        assert_eq!((count(&high), count(&low)), (1, 0));
+        sender.send(6).unwrap();
+        assert_eq!((count(&high), count(&low)), (1, 0));
+
+        // 7 -> 2 buffered messages doesn't go below the low watermark yet
+        for _ in 0..5 {
+            mrx.receive();
+        }
+        assert_eq!((count(&high), count(&low)), (1, 0));
+        mrx.receive();
+        assert_eq!((count(&high), count(&low)), (1, 1));
+
+        // Going back up across the high watermark fires again
+        for x in 0..5 {
+            sender.send(x).unwrap();
+        }
+        assert_eq!((count(&high), count(&low)), (2, 1));
+
+        // Removing the channel drops its backlog
+        mrx.remove_channel(&sender);
+        assert_eq!((count(&high), count(&low)), (2, 2));
+    }
+
+    #[test]
+    fn watermarks_failed_sends() {
+        let mrx = DynMultiReceiver::<i32, u16>::new();
+        let high = Arc::new(AtomicUsize::new(0));
+        let high_clone = high.clone();
+        mrx.on_high_watermark(2, move || {
+            high_clone.fetch_add(1, Ordering::Relaxed);
+        });
+        let sender = mrx.new_channel(10, 10, false, Some(2));
+        sender.send(0).unwrap();
+        sender.send(1).unwrap();
+        // Sends are counted before they are attempted, but only announced once they succeeded
+        assert_eq!(sender.try_send(2), Err(TrySendError::Full(2)));
+        assert_eq!(
+            sender.send_timeout(3, Duration::from_millis(10)),
+            Err(SendTimeoutError::Timeout(3))
+        );
+        assert_eq!(high.load(Ordering::Relaxed), 0);
+        assert_eq!(mrx.receive(), 0);
+        assert_eq!(mrx.receive(), 1);
+        assert_eq!(mrx.try_receive(), Err(TryReceiveError::Empty));
+        // The failed sends were rolled back, so two sends reach the watermark again without crossing it
+        sender.send(4).unwrap();
+        sender.send(5).unwrap();
+        assert_eq!(high.load(Ordering::Relaxed), 0);
+    }
+
+    #[test]
+    fn watermarks_raw_receiver_after_removal() {
+        let mrx = DynMultiReceiver::<i32, u16>::new();
+        let high = Arc::new(AtomicUsize::new(0));
+        let high_clone = high.clone();
+        mrx.on_high_watermark(2, move || {
+            high_clone.fetch_add(1, Ordering::Relaxed);
+        });
+        let sender = mrx.new_channel(10, 10, false, None);
+        let raw = mrx.raw_receiver(sender.id()).unwrap();
+        sender.send_all([0, 1]).unwrap();
+        assert!(mrx.remove_channel(&sender));
+        // The messages stay with the raw receiver and are only accounted once taken from there
+        assert_eq!(raw.try_recv(), Ok(0));
+        assert_eq!(raw.try_recv(), Ok(1));
+        sender.send_all([2, 3]).unwrap();
+        assert_eq!(raw.try_recv(), Ok(2));
+        // The message left over is accounted once the raw receiver is dropped as well
+        drop(raw);
+        assert_eq!(high.load(Ordering::Relaxed), 0);
+
+        let fresh = mrx.new_channel(10, 10, false, None);
+        fresh.send_all([4, 5]).unwrap();
+        assert_eq!(high.load(Ordering::Relaxed), 0);
+        fresh.send(6).unwrap();
+        assert_eq!(high.load(Ordering::Relaxed), 1);
+    }
+
+    #[test]
+    fn limited_channel() {
+        let mrx = DynMultiReceiver::<i32, u16>::new();
+        let other = mrx.new_channel(10, 10, false, None);
+        let limited = mrx.new_limited_channel(1, 10, false, None, 3);
+        for x in 0..3 {
+            limited.send(x).unwrap();
+        }
+        assert!(matches!(limited.send(3), Err(SendError::Exhausted)));
+        other.send(10).unwrap();
+
+        for x in 0..3 {
+            assert!(mrx.channels_at_priority(&1).len() == 1);
+            assert_eq!(mrx.receive(), x);
+        }
+        assert!(mrx.channels_at_priority(&1).is_empty());
+        assert_eq!(mrx.receive(), 10);
+        mrx.remove_channel(&other);
+        assert!(mrx.no_channels());
+    }
+
+    #[test]
+    fn capacity() {
+        let mrx = DynMultiReceiver::<i32, u16>::new();
+        let unbounded = mrx.new_channel(1, 1, false, None);
+        for x in 0..100 {
+            unbounded.send(x).unwrap();
+        }
+        assert_eq!(unbounded.capacity(), None);
+        assert!(!unbounded.is_full());
+
+        let bounded = mrx.new_channel(1, 1, false, Some(2));
+        assert_eq!(bounded.capacity(), Some(2));
+        bounded.send(0).unwrap();
+        assert!(!bounded.is_full());
+        bounded.send(1).unwrap();
+        assert!(bounded.is_full());
+
+        let rendezvous = mrx.new_channel(1, 1, false, Some(0));
+        assert_eq!(rendezvous.capacity(), Some(0));
+        assert!(rendezvous.is_full());
+
+        let reserve = mrx.new_channel_with_reserve(1, 1, false, 1, 1);
+        assert_eq!(reserve.capacity(), Some(2));
+        reserve.send(0).unwrap();
+        assert!(reserve.is_full());
+    }
+
+    #[test]
+    fn rendezvous_is_full_without_waiting_receiver() {
+        let mrx = Arc::new(DynMultiReceiver::<i32, u16>::new());
+        let rendezvous = mrx.new_channel(1, 1, false, Some(0));
+        assert!(rendezvous.is_full());
+        let receiving = mrx.clone();
+        let receiver = std::thread::spawn(move || receiving.receive());
+        // Not full anymore once the receiver waits for a message
+        let start = Instant::now();
+        while rendezvous.is_full() {
+            assert!(start.elapsed() < Duration::from_secs(10));
+            std::thread::yield_now();
+        }
+        rendezvous.send(1).unwrap();
+        assert_eq!(receiver.join().unwrap(), 1);
+        assert!(rendezvous.is_full());
+    }
+
+    #[test]
+    fn try_send() {
+        let mrx = DynMultiReceiver::<i32, u16>::new();
+        let bounded = mrx.new_channel(1, 1, false, Some(2));
+        bounded.try_send(0).unwrap();
+        bounded.try_send(1).unwrap();
+        let full = bounded.try_send(2).unwrap_err();
+        assert_eq!(full, TrySendError::Full(2));
+        assert_eq!(full.into_inner(), 2);
+        assert_eq!(mrx.receive(), 0);
+        bounded.try_send(2).unwrap();
+
+        // Rejected messages don't use up the sends of a limited channel
+        let limited = mrx.new_limited_channel(0, 1, false, Some(1), 2);
+        limited.try_send(10).unwrap();
+        assert_eq!(limited.try_send(11), Err(TrySendError::Full(11)));
+        assert_eq!(mrx.receive(), 10);
+        limited.try_send(11).unwrap();
+        assert_eq!(mrx.receive(), 11);
+        assert_eq!(limited.try_send(12), Err(TrySendError::Exhausted(12)));
+
+        let reserve = mrx.new_channel_with_reserve(2, 1, false, 1, 1);
+        reserve.try_send(20).unwrap();
+        assert_eq!(reserve.try_send(21), Err(TrySendError::Full(21)));
+        let rendezvous = mrx.new_channel(2, 1, false, Some(0));
+        assert_eq!(rendezvous.try_send(30), Err(TrySendError::Full(30)));
+        mrx.remove_channel(&rendezvous);
+        assert_eq!(rendezvous.try_send(30), Err(TrySendError::Disconnected(30)));
+        for x in [1, 2, 20] {
+            assert_eq!(mrx.receive(), x);
+        }
+    }
+
+    #[test]
+    fn send_timeout() {
+        let mrx = DynMultiReceiver::<i32, u16>::new();
+        let bounded = mrx.new_limited_channel(1, 1, false, Some(1), 2);
+        bounded.send(0).unwrap();
+        let start = Instant::now();
+        let timeout = bounded
+            .send_timeout(1, Duration::from_millis(50))
+            .unwrap_err();
+        assert!(start.elapsed() >= Duration::from_millis(50));
+        assert_eq!(timeout, SendTimeoutError::Timeout(1));
+        assert_eq!(timeout.into_inner(), 1);
+
+        // The timed out message didn't use up a send of the limited channel
+        let receiving = {
+            let mrx = mrx.clone();
+            std::thread::spawn(move || {
+                std::thread::sleep(Duration::from_millis(50));
+                mrx.receive()
+            })
+        };
+        bounded.send_timeout(1, Duration::from_secs(10)).unwrap();
+        assert_eq!(receiving.join().unwrap(), 0);
+        assert_eq!(mrx.receive(), 1);
+        assert_eq!(
+            bounded.send_timeout(2, Duration::from_millis(10)),
+            Err(SendTimeoutError::Exhausted(2))
+        );
+
+        let rendezvous = mrx.new_channel(1, 1, false, Some(0));
+        assert_eq!(
+            rendezvous.send_timeout(3, Duration::from_millis(10)),
+            Err(SendTimeoutError::Timeout(3))
+        );
+        let receiving = {
+            let mrx = mrx.clone();
+            std::thread::spawn(move || mrx.receive())
+        };
+        rendezvous.send_timeout(4, Duration::from_secs(10)).unwrap();
+        assert_eq!(receiving.join().unwrap(), 4);
+    }
+
+    #[test]
+    fn receive_coalesced() {
+        let mrx = DynMultiReceiver::<i32, u16>::new();
+        let high = mrx.new_channel(1, 10, false, None);
+        let low = mrx.new_channel(2, 10, false, None);
+        for x in 1..=4 {
+            high.send(x).unwrap();
+            low.send(x * 10).unwrap();
+        }
+        assert_eq!(mrx.receive_coalesced(|a, b| a + b), 10);
+        // Keep the latest value only
+        assert_eq!(mrx.receive_coalesced(|_, b| b), 40);
+        high.send(5).unwrap();
+        assert_eq!(mrx.receive_coalesced(|a, b| a + b), 5);
+        assert_eq!(mrx.total_received(), 9);
+    }
+
+    #[test]
+    fn pending_wakes() {
+        let mrx = DynMultiReceiver::<i32, u16>::new();
+        let sender = mrx.new_channel(10, 10, false, None);
+        assert_eq!(mrx.pending_wakes(), 0);
+        for x in 0..3 {
+            sender.send(x).unwrap();
+        }
+        assert_eq!(mrx.pending_wakes(), 3);
+        mrx.receive();
+        assert_eq!(mrx.pending_wakes(), 2);
+    }
+
+    #[test]
+    fn total_received() {
+        let mrx = DynMultiReceiver::<i32, u16>::new();
+        let mrx2 = mrx.clone();
+        let sender = mrx.new_channel(10, 10, false, None);
+        for x in 0..100 {
+            sender.send(x).unwrap();
+        }
+        for _ in 0..60 {
+            mrx.receive();
+        }
+        for _ in 0..30 {
+            mrx2.receive();
+        }
+        assert_eq!(mrx.total_received(), 90);
+        assert_eq!(mrx2.total_received(), 90);
+    }
+
+    #[test]
+    fn pin_tier_first() {
+        let mrx = DynMultiReceiver::<i32, u16>::new();
+        let high = mrx.new_channel(1, 10, false, None);
+        let mid = mrx.new_channel(5, 10, false, None);
+        let low = mrx.new_channel(9, 10, false, None);
+        for x in 0..3 {
+            high.send(x).unwrap();
+            mid.send(10 + x).unwrap();
+            low.send(20 + x).unwrap();
+        }
+
+        mrx.pin_tier_first(9);
+        assert_eq!(mrx.receive(), 20);
+        assert_eq!(mrx.receive(), 21);
+        mrx.unpin_tier();
+        assert_eq!(mrx.receive(), 0);
+        mrx.pin_tier_first(5);
+        for x in 10..13 {
+            assert_eq!(mrx.receive(), x);
+        }
+        // The pinned tier is empty, so the normal order applies again
+        assert_eq!(mrx.receive(), 1);
+        mrx.unpin_tier();
+        assert_eq!(mrx.receive(), 2);
+        assert_eq!(mrx.receive(), 22);
+    }
+
     #[test]
     fn freeze_unfreeze() {
         let mrx = DynMultiReceiver::<i32, u16>::new();
@@ -240,45 +730,2268 @@ mod tests {
     }
 
     #[test]
-    fn one_sender_many_receivers() {
-        let barrier = Arc::new(Barrier::new(100));
+    fn channels_at_priority() {
         let mrx = DynMultiReceiver::<i32, u16>::new();
-        let sender = mrx.new_channel(10, 10, false, None);
-        let mut receivers = Vec::new();
-        for _ in 0..100 {
-            receivers.push(mrx.clone());
+        let a = mrx.new_channel(1, 10, false, None);
+        let _b = mrx.new_channel(2, 20, false, Some(5));
+        let c = mrx.new_channel(1, 30, true, Some(7));
+        let _d = mrx.new_channel(3, 40, false, None);
+
+        let mut tier = mrx.channels_at_priority(&1);
+        tier.sort_unstable();
+        assert_eq!(tier, vec![(a.id(), 10, None), (c.id(), 30, Some(7))]);
+        assert!(mrx.channels_at_priority(&4).is_empty());
+    }
+
+    #[test]
+    fn freeze_snapshot_restore() {
+        let mrx = DynMultiReceiver::<i32, u16>::new();
+        let a = mrx.new_channel(1, 10, false, None);
+        let b = mrx.new_channel(1, 10, true, None);
+        let c = mrx.new_channel(2, 10, false, None);
+        let d = mrx.new_channel(3, 10, true, None);
+
+        let snapshot = mrx.freeze_snapshot();
+        assert_eq!(snapshot.len(), 4);
+        assert_eq!(snapshot.get(a.id()), Some(false));
+        assert_eq!(snapshot.get(b.id()), Some(true));
+
+        mrx.set_frozen_many([a.id(), c.id()], true);
+        mrx.set_frozen_many([b.id(), d.id()], false);
+        mrx.remove_channel(&d);
+        mrx.restore_freeze(&snapshot);
+
+        let restored = mrx.freeze_snapshot();
+        assert_eq!(restored.len(), 3);
+        for sender in [&a, &b, &c] {
+            assert_eq!(restored.get(sender.id()), snapshot.get(sender.id()));
         }
+        assert_eq!(restored.get(d.id()), None);
+    }
 
-        for x in 0..100 {
-            sender.send(x).unwrap();
+    #[test]
+    fn channels() {
+        let mrx = DynMultiReceiver::<i32, u16>::new();
+        assert!(mrx.channels().is_empty());
+        let a = mrx.new_channel(2, 5, false, None);
+        let b = mrx.new_channel(1, 3, true, Some(4));
+        let c = mrx.new_channel(2, 7, false, None);
+        a.send(1).unwrap();
+        b.send(2).unwrap();
+        b.send(3).unwrap();
+        let info =
+            |sender: &crate::DynSender<i32, u16>, priority, weight, frozen, queued| ChannelInfo {
+                id: sender.id(),
+                priority,
+                weight,
+                frozen,
+                queued,
+            };
+        assert_eq!(
+            mrx.channels(),
+            vec![
+                info(&b, 1, 3, true, 2),
+                info(&a, 2, 5, false, 1),
+                info(&c, 2, 7, false, 0)
+            ]
+        );
+        // The head of c is peeked while looking for the oldest message, but still counts
+        c.send(4).unwrap();
+        c.send(5).unwrap();
+        assert_eq!(mrx.receive_oldest(), 1);
+        assert_eq!(mrx.channels()[2], info(&c, 2, 7, false, 2));
+        mrx.remove_channel(&a);
+        assert_eq!(mrx.channels().len(), 2);
+    }
+
+    #[test]
+    fn with_hasher() {
+        use std::{collections::hash_map::RandomState, hash::BuildHasherDefault};
+
+        let mrx = DynMultiReceiver::<u32, u16, RandomState>::with_hasher(RandomState::new());
+        let senders: Vec<_> = (0..10)
+            .map(|i| mrx.new_channel(i % 3, 1, false, None))
+            .collect();
+        for sender in &senders {
+            sender.send(sender.id()).unwrap();
         }
+        mrx.remove_channel(&senders[0]);
+        let mut received: Vec<_> = (0..9).map(|_| mrx.receive_with_id()).collect();
+        received.sort_unstable();
+        assert_eq!(received, (1..10).map(|id| (id, id)).collect::<Vec<_>>());
+        drop(senders);
+        assert!(mrx.no_channels());
 
-        let mut receiver_threads = Vec::new();
-        for receiver in receivers {
-            let barrier = barrier.clone();
-            receiver_threads.push(std::thread::spawn(move || {
-                barrier.wait();
-                receiver.receive()
-            }));
+        // A deterministic hasher
+        type Fixed = BuildHasherDefault<std::collections::hash_map::DefaultHasher>;
+        let mrx = DynMultiReceiver::<u32, u16, Fixed>::default();
+        let sender = mrx.new_channel(1, 1, false, None);
+        sender.send(1).unwrap();
+        assert_eq!(mrx.receive(), 1);
+    }
+
+    #[test]
+    fn default() {
+        #[derive(Default)]
+        struct Consumer {
+            mrx: DynMultiReceiver<u32, u16>,
         }
+        let consumer = Consumer::default();
+        assert!(consumer.mrx.no_channels());
+        let sender = consumer.mrx.new_channel(1, 1, false, None);
+        sender.send(1).unwrap();
+        assert_eq!(consumer.mrx.receive(), 1);
+    }
 
-        let mut received = Vec::new();
-        for thread in receiver_threads {
-            received.push(thread.join().unwrap());
+    #[test]
+    fn debug() {
+        // The message type doesn't implement Debug
+        struct Message;
+        let mrx = DynMultiReceiver::<Message, u16>::new();
+        let _a = mrx.new_channel(1, 1, true, None);
+        let b = mrx.new_channel(1, 1, false, None);
+        let _c = mrx.new_channel(5, 1, false, None);
+        b.send(Message).unwrap();
+        assert_eq!(
+            format!("{:?}", mrx),
+            "DynMultiReceiver { groups: 2, channels: 3, tiers: [\
+             Tier { priority: 1, channels: 2, frozen: 1 }, \
+             Tier { priority: 5, channels: 1, frozen: 0 }], .. }"
+        );
+    }
+
+    #[test]
+    fn stats() {
+        let mrx = DynMultiReceiver::<u32, u16>::new();
+        let busy = mrx.new_channel(1, 1, false, None);
+        let idle = mrx.new_channel(2, 1, false, None);
+        busy.send_all(0..10).unwrap();
+        for _ in 0..4 {
+            mrx.receive();
         }
-        received.sort_unstable();
-        for (idx, msg) in received.iter().enumerate() {
-            assert_eq!(idx, *msg as usize);
+        assert_eq!(
+            mrx.stats(busy.id()),
+            Some(ChannelStats {
+                sent: 10,
+                received: 4,
+                current_len: 6,
+            })
+        );
+        assert_eq!(mrx.stats(idle.id()), Some(ChannelStats::default()));
+        assert_eq!(mrx.stats(42), None);
+
+        // The head of idle is peeked while looking for the oldest message, but still counts
+        idle.send(10).unwrap();
+        assert_eq!(mrx.receive_oldest(), 4);
+        assert_eq!(mrx.stats(busy.id()).unwrap().current_len, 5);
+        assert_eq!(mrx.stats(idle.id()).unwrap().current_len, 1);
+    }
+
+    #[test]
+    fn set_group_frozen() {
+        let mrx = DynMultiReceiver::<i32, u16>::new();
+        let high = [
+            mrx.new_channel(2, 1, false, None),
+            mrx.new_channel(2, 1, false, None),
+        ];
+        let low = mrx.new_channel(1, 1, false, None);
+        for (i, sender) in high.iter().enumerate() {
+            sender.send(i as i32).unwrap();
         }
+        low.send(10).unwrap();
+
+        mrx.set_group_frozen(&2, true);
+        mrx.set_group_frozen(&3, true);
+        assert!(high.iter().all(|sender| sender.is_frozen()));
+        assert!(!low.is_frozen());
+        assert_eq!(mrx.receive(), 10);
+        assert_eq!(mrx.try_receive(), Err(TryReceiveError::Empty));
+
+        mrx.set_group_frozen(&2, false);
+        let mut received = [mrx.receive(), mrx.receive()];
+        received.sort_unstable();
+        assert_eq!(received, [0, 1]);
     }
 
     #[test]
-    fn disconnect() {
+    fn freeze_all() {
+        let mrx = DynMultiReceiver::<i32, u16>::new();
+        let a = mrx.new_channel(1, 1, false, None);
+        let b = mrx.new_channel(2, 1, true, None);
+        a.send(1).unwrap();
+        mrx.freeze_all();
+        assert!(a.is_frozen() && b.is_frozen());
+
+        let (tx, rx) = crossbeam_channel::unbounded();
+        let receiver = mrx.clone();
+        let receiving = std::thread::spawn(move || tx.send(receiver.receive()).unwrap());
+        assert!(rx.recv_timeout(Duration::from_millis(100)).is_err());
+
+        mrx.unfreeze_all();
+        assert!(!a.is_frozen() && !b.is_frozen());
+        assert_eq!(rx.recv_timeout(Duration::from_secs(5)), Ok(1));
+        receiving.join().unwrap();
+    }
+
+    #[test]
+    fn freeze_toggle_during_receive() {
+        let mrx = DynMultiReceiver::<i32, u16>::new();
+        let sender = mrx.new_channel(1, 1, false, None);
+        sender.send(1).unwrap();
+
+        // A receive in progress holds the read lock, freezing must not wait for it
+        let scanning = mrx.state.read().unwrap();
+        let (tx, rx) = crossbeam_channel::unbounded();
+        let toggling = std::thread::spawn(move || {
+            for i in 0..1000 {
+                sender.set_frozen(i % 2 == 0);
+            }
+            tx.send(()).unwrap();
+            sender
+        });
+        assert_eq!(rx.recv_timeout(Duration::from_secs(5)), Ok(()));
+        drop(scanning);
+        let sender = toggling.join().unwrap();
+        assert!(!sender.is_frozen());
+        assert_eq!(mrx.receive(), 1);
+    }
+
+    #[test]
+    fn unfreeze_wakes_receiver() {
         let mrx = DynMultiReceiver::<i32, u16>::new();
         let sender = mrx.new_channel(10, 10, false, None);
-        mrx.remove_channel(&sender);
-        assert!(mrx.no_channels());
-        assert!(sender.send(0).is_err());
+        sender.send(7).unwrap();
+        sender.set_frozen(true);
+
+        let (tx, rx) = crossbeam_channel::unbounded();
+        let mrx2 = mrx.clone();
+        std::thread::spawn(move || tx.send(mrx2.receive()).unwrap());
+        // The receiver took the wake of the frozen message and is parked now
+        assert!(rx.recv_timeout(Duration::from_millis(100)).is_err());
+        assert_eq!(mrx.pending_wakes(), 0);
+
+        std::thread::spawn(move || sender.set_frozen(false))
+            .join()
+            .unwrap();
+        assert_eq!(rx.recv_timeout(Duration::from_secs(1)), Ok(7));
+    }
+
+    #[test]
+    fn link_priority() {
+        let mrx = DynMultiReceiver::<i32, u16>::new();
+        let a = mrx.new_channel(1, 10, true, None);
+        let c = mrx.new_channel(2, 10, false, None);
+        let b = mrx.new_channel(3, 10, false, None);
+        assert_eq!(mrx.link_priority(a.id(), b.id()), Ok(()));
+        assert_eq!(mrx.link_priority(b.id(), a.id()), Err(LinkError::Cycle));
+        assert_eq!(mrx.link_priority(a.id(), a.id()), Err(LinkError::Cycle));
+        assert_eq!(
+            mrx.link_priority(a.id(), 1000),
+            Err(LinkError::UnknownChannel(1000))
+        );
+
+        // Without a backlog in a, b keeps its own priority
+        c.send(200).unwrap();
+        b.send(100).unwrap();
+        assert_eq!(mrx.receive(), 200);
+        assert_eq!(mrx.receive(), 100);
+
+        // The frozen backlog of a promotes b above c
+        a.send(0).unwrap();
+        for x in 201..204 {
+            c.send(x).unwrap();
+        }
+        for x in 101..104 {
+            b.send(x).unwrap();
+        }
+        for x in 101..104 {
+            assert_eq!(mrx.receive(), x);
+        }
+
+        // Once a is drained, b falls back behind c
+        a.set_frozen(false);
+        assert_eq!(mrx.receive(), 0);
+        b.send(104).unwrap();
+        assert_eq!(mrx.receive(), 201);
+
+        assert!(mrx.unlink_priority(a.id(), b.id()));
+        assert!(!mrx.unlink_priority(a.id(), b.id()));
+    }
+
+    #[test]
+    fn many_idle_channels() {
+        let mrx = DynMultiReceiver::<usize, u16>::new();
+        let mut senders: Vec<_> = (0..1000)
+            .map(|_| mrx.new_channel(1, 10, false, None))
+            .collect();
+        // Reuse the slots of removed channels
+        for sender in senders.drain(100..200) {
+            mrx.remove_channel(&sender);
+        }
+        senders.extend((0..150).map(|_| mrx.new_channel(1, 10, false, None)));
+
+        let mut rng = thread_rng();
+        let mut expected = Vec::new();
+        for _ in 0..50 {
+            let idx = rng.gen_range(4..senders.len());
+            senders[idx].send(idx).unwrap();
+            expected.push(idx);
+        }
+        let frozen = &senders[3];
+        frozen.set_frozen(true);
+        frozen.send(3).unwrap();
+        let mut received: Vec<_> = (0..50).map(|_| mrx.receive()).collect();
+        received.sort_unstable();
+        expected.sort_unstable();
+        assert_eq!(received, expected);
+
+        frozen.set_frozen(false);
+        assert_eq!(mrx.receive(), 3);
+    }
+
+    #[test]
+    fn receive_with_rng() {
+        let sequence = || {
+            let mrx = DynMultiReceiver::<u32, u16>::new();
+            for weight in 1..5 {
+                let sender = mrx.new_channel(1, weight, false, None);
+                for _ in 0..20 {
+                    sender.send(weight).unwrap();
+                }
+            }
+            let mut rng = SmallRng::seed_from_u64(42);
+            (0..40)
+                .map(|_| mrx.receive_with_rng(&mut rng))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(sequence(), sequence());
+    }
+
+    #[test]
+    fn with_seed() {
+        let sequence = || {
+            let mrx = DynMultiReceiver::<u32, u16>::new().with_seed(42);
+            for weight in 1..5 {
+                let sender = mrx.new_channel(1, weight, false, None);
+                for _ in 0..20 {
+                    sender.send(weight).unwrap();
+                }
+            }
+            let clone = mrx.clone();
+            (0..40)
+                .map(|i| if i % 2 == 0 { &mrx } else { &clone }.receive())
+                .collect::<Vec<_>>()
+        };
+        let first = sequence();
+        assert_eq!(first, sequence());
+        assert!(first.iter().any(|&weight| weight != first[0]));
+    }
+
+    #[test]
+    fn try_recv_from() {
+        let mrx = DynMultiReceiver::<u32, u16>::new();
+        let high = mrx.new_channel(1, 1, false, None);
+        let low = mrx.new_channel(2, 1, true, None);
+        high.send(1).unwrap();
+        low.send(2).unwrap();
+        low.send(3).unwrap();
+        // Frozen and lower priority channels can be drained by id
+        assert_eq!(mrx.try_recv_from(low.id()), Ok(2));
+        assert_eq!(mrx.try_recv_from(low.id()), Ok(3));
+        assert_eq!(mrx.try_recv_from(low.id()), Err(TryRecvFromError::Empty));
+        assert_eq!(mrx.try_recv_from(42), Err(TryRecvFromError::NotFound));
+        assert_eq!(mrx.len(), 1);
+        assert_eq!(mrx.receive(), 1);
+
+        // A channel with a message keeps existing after its sender is dropped
+        let id = high.id();
+        high.send(4).unwrap();
+        drop(high);
+        assert_eq!(mrx.try_recv_from(id), Ok(4));
+        assert_eq!(mrx.try_recv_from(id), Err(TryRecvFromError::NotFound));
+    }
+
+    #[test]
+    fn id_wraparound() {
+        let mrx = DynMultiReceiver::<u32, u16>::new();
+        let a = mrx.new_channel(1, 1, false, None);
+        let b = mrx.new_channel(1, 1, false, None);
+        mrx.remove_channel(&b);
+        mrx.state.write().unwrap().next_id = u32::MAX;
+        let c = mrx.new_channel(1, 1, false, None);
+        // 0 is still used by a, 1 was freed by b
+        let d = mrx.new_channel(2, 1, false, None);
+        assert_eq!([a.id(), c.id(), d.id()], [0, u32::MAX, 1]);
+        for sender in [&a, &c, &d] {
+            sender.send(sender.id()).unwrap();
+        }
+        let mut received: Vec<_> = (0..3).map(|_| mrx.receive_with_id()).collect();
+        received.sort_unstable();
+        assert_eq!(received, vec![(0, 0), (1, 1), (u32::MAX, u32::MAX)]);
+    }
+
+    #[test]
+    fn stale_sender_after_id_reuse() {
+        let mrx = DynMultiReceiver::<u32, u16>::new();
+        let _keep = mrx.new_channel(1, 1, false, None);
+        for drop_under_lock in [false, true] {
+            mrx.state.write().unwrap().next_id = u32::MAX;
+            let stale = mrx.new_channel(1, 1, false, None);
+            mrx.remove_channel(&stale);
+            mrx.state.write().unwrap().next_id = u32::MAX;
+            let fresh = mrx.new_channel(1, 1, false, None);
+            assert_eq!(fresh.id(), stale.id());
+            // The stale sender can't reach the newer channel
+            stale.set_frozen(true);
+            stale.set_weight(5);
+            stale.set_priority(0);
+            assert!(!stale.is_frozen());
+            assert!(!mrx.remove_channel(&stale));
+            assert!(mrx.remove_channel_draining(&stale).is_empty());
+            let info = mrx
+                .channels()
+                .into_iter()
+                .find(|c| c.id == fresh.id())
+                .unwrap();
+            assert_eq!((info.priority, info.weight, info.frozen), (1, 1, false));
+            if drop_under_lock {
+                // The removal is left to the next receive then
+                let _state = mrx.state.read().unwrap();
+                drop(stale);
+            } else {
+                drop(stale);
+            }
+            // Runs the cleanup while the newer channel is empty
+            assert!(mrx.try_receive().is_err());
+            assert!(mrx.stats(fresh.id()).is_some());
+            fresh.send(1).unwrap();
+            assert_eq!(mrx.receive(), 1);
+            mrx.remove_channel(&fresh);
+        }
+    }
+
+    #[test]
+    fn receive_with_id() {
+        let mrx = DynMultiReceiver::<u32, u16>::new();
+        let a = mrx.new_channel(1, 1, false, None);
+        let b = mrx.new_channel(1, 1, false, None);
+        a.send(1).unwrap();
+        b.send(2).unwrap();
+        b.send(3).unwrap();
+        let mut received = (0..3).map(|_| mrx.receive_with_id()).collect::<Vec<_>>();
+        received.sort_unstable_by_key(|&(_, value)| value);
+        assert_eq!(received, vec![(a.id(), 1), (b.id(), 2), (b.id(), 3)]);
+    }
+
+    /// A waker for a minimal executor, which parks the thread while the future is pending.
+    #[cfg(feature = "async")]
+    fn unpark_waker() -> std::task::Waker {
+        struct Unpark(std::thread::Thread);
+        impl std::task::Wake for Unpark {
+            fn wake(self: Arc<Self>) {
+                self.0.unpark();
+            }
+        }
+        std::task::Waker::from(Arc::new(Unpark(std::thread::current())))
+    }
+
+    #[cfg(feature = "async")]
+    #[test]
+    fn receive_async() {
+        use std::{
+            future::Future,
+            task::{Context, Poll},
+        };
+
+        let waker = unpark_waker();
+        let mut cx = Context::from_waker(&waker);
+
+        let mrx = DynMultiReceiver::<u32, u16>::new();
+        let sender = mrx.new_channel(1, 1, false, None);
+        let frozen = mrx.new_channel(0, 1, true, None);
+        frozen.send(0).unwrap();
+        let mut receive = std::pin::pin!(mrx.receive_async());
+        assert!(receive.as_mut().poll(&mut cx).is_pending());
+        let sending = std::thread::spawn(move || {
+            std::thread::sleep(Duration::from_millis(50));
+            sender.send(1).unwrap();
+        });
+        let value = loop {
+            if let Poll::Ready(value) = receive.as_mut().poll(&mut cx) {
+                break value;
+            }
+            std::thread::park();
+        };
+        assert_eq!(value, 1);
+        sending.join().unwrap();
+    }
+
+    #[cfg(feature = "async")]
+    #[test]
+    fn into_stream() {
+        use futures_core::Stream;
+        use std::task::{Context, Poll};
+
+        // Only polled through the trait, like the stream combinators of the futures crate do
+        fn poll_stream<St: Stream + Unpin>(
+            stream: &mut St,
+            cx: &mut Context<'_>,
+        ) -> Poll<Option<St::Item>> {
+            std::pin::Pin::new(stream).poll_next(cx)
+        }
+
+        let waker = unpark_waker();
+        let mut cx = Context::from_waker(&waker);
+
+        let mrx = DynMultiReceiver::<u32, u16>::new();
+        let producers: Vec<_> = (0..4)
+            .map(|p| {
+                let sender = mrx.new_channel(p % 2, 4, false, None);
+                std::thread::spawn(move || {
+                    for i in 0..100 {
+                        sender.send(p as u32 * 100 + i).unwrap();
+                    }
+                })
+            })
+            .collect();
+        let mut stream = mrx.into_stream();
+        let mut received = Vec::new();
+        loop {
+            match poll_stream(&mut stream, &mut cx) {
+                Poll::Ready(Some(value)) => received.push(value),
+                Poll::Ready(None) => break,
+                Poll::Pending => std::thread::park(),
+            }
+        }
+        for producer in producers {
+            producer.join().unwrap();
+        }
+        received.sort_unstable();
+        assert_eq!(received, (0..400).collect::<Vec<_>>());
+        assert!(stream.into_inner().no_channels());
+    }
+
+    #[cfg(feature = "metrics")]
+    #[test]
+    fn metrics_text() {
+        let mrx = DynMultiReceiver::<i32, u16>::new();
+        let a = mrx.new_channel(1, 10, false, None);
+        let _b = mrx.new_channel(1, 10, true, None);
+        let c = mrx.new_channel(2, 10, false, None);
+        a.send(1).unwrap();
+        c.send(2).unwrap();
+        c.send(3).unwrap();
+        mrx.receive();
+
+        let text = mrx.metrics_text();
+        let mut samples = Vec::new();
+        for line in text.lines().filter(|l| !l.starts_with('#')) {
+            let (name, value) = line.rsplit_once(' ').unwrap();
+            samples.push((name.to_string(), value.parse::<f64>().unwrap()));
+        }
+        let pending: f64 = samples
+            .iter()
+            .filter(|(name, _)| name.starts_with("multichannel_pending{priority="))
+            .map(|(_, value)| value)
+            .sum();
+        assert_eq!(pending, 2.0);
+        for (name, value) in [
+            ("multichannel_channels", 3.0),
+            ("multichannel_groups", 2.0),
+            ("multichannel_received_total", 1.0),
+            ("multichannel_frozen_channels", 1.0),
+        ] {
+            assert!(samples.contains(&(name.to_string(), value)), "{}", name);
+            assert!(text.contains(&format!("# TYPE {} ", name)));
+        }
+    }
+
+    #[test]
+    fn new_channel_with_first() {
+        let mrx = DynMultiReceiver::<i32, u16>::new();
+        let mrx2 = mrx.clone();
+        let receiver = std::thread::spawn(move || mrx2.receive());
+        std::thread::sleep(Duration::from_millis(50));
+
+        let sender = mrx.new_channel_with_first(1, 10, false, Some(1), 42);
+        assert_eq!(receiver.join().unwrap(), 42);
+        sender.send(43).unwrap();
+        assert_eq!(mrx.receive(), 43);
+        assert_eq!(mrx.pending_wakes(), 0);
+    }
+
+    #[test]
+    fn reap_idle() {
+        let mrx = DynMultiReceiver::<i32, u16>::new();
+        let active = mrx.new_channel(1, 10, false, None);
+        let idle = mrx.new_channel(1, 10, false, None);
+        idle.send(0).unwrap();
+        assert_eq!(mrx.receive(), 0);
+        assert!(mrx.reap_idle(Duration::from_millis(100)).is_empty());
+
+        std::thread::sleep(Duration::from_millis(150));
+        active.send(1).unwrap();
+        assert_eq!(mrx.reap_idle(Duration::from_millis(100)), vec![idle.id()]);
+        assert!(idle.send(2).is_err());
+        assert_eq!(mrx.receive(), 1);
+    }
+
+    #[test]
+    fn recv_ready_signal() {
+        let mrx = DynMultiReceiver::<u32, u16>::new();
+        let sender = mrx.new_channel(1, 1, false, None);
+        sender.send(0).unwrap();
+        let ready = mrx.recv_ready_signal();
+        let (other_tx, other_rx) = crossbeam_channel::unbounded::<u32>();
+
+        let producing = std::thread::spawn(move || {
+            for i in 1..100 {
+                sender.send(i).unwrap();
+                if i % 10 == 0 {
+                    other_tx.send(i).unwrap();
+                }
+            }
+        });
+        let mut received = Vec::new();
+        let mut other = Vec::new();
+        while received.len() < 100 || other.len() < 9 {
+            let mut select = crossbeam_channel::Select::new();
+            let ready_idx = select.recv(&ready);
+            // The other channel disconnects once the producer is done
+            if other.len() < 9 {
+                select.recv(&other_rx);
+            }
+            let operation = select.select_timeout(Duration::from_secs(5)).unwrap();
+            if operation.index() == ready_idx {
+                operation.recv(&ready).unwrap();
+                received.extend(mrx.try_iter());
+            } else {
+                other.push(operation.recv(&other_rx).unwrap());
+            }
+        }
+        producing.join().unwrap();
+        assert_eq!(received, (0..100).collect::<Vec<_>>());
+        assert_eq!(other, (1..10).map(|i| i * 10).collect::<Vec<_>>());
+        assert_eq!(mrx.condvar.0.lock().unwrap().signals.len(), 1);
+        drop(ready);
+        // Dropped signals are cleaned up by the next wake
+        mrx.new_channel(1, 1, false, None).send(0).unwrap();
+        assert!(mrx.condvar.0.lock().unwrap().signals.is_empty());
+    }
+
+    #[test]
+    fn receive_many() {
+        let mrx = DynMultiReceiver::<i32, u16>::new();
+        let high = mrx.new_channel(1, 1, false, None);
+        let low = mrx.new_channel(2, 1, false, None);
+        for i in 0..3 {
+            low.send(10 + i).unwrap();
+            high.send(i).unwrap();
+        }
+        let mut out = vec![-1];
+        assert_eq!(mrx.receive_many(0, &mut out), 0);
+        assert_eq!(mrx.receive_many(4, &mut out), 4);
+        assert_eq!(out, vec![-1, 0, 1, 2, 10]);
+        // Only what is deliverable right now, without waiting for more
+        out.clear();
+        assert_eq!(mrx.receive_many(10, &mut out), 2);
+        assert_eq!(out, vec![11, 12]);
+        let sender = std::thread::spawn(move || {
+            std::thread::sleep(Duration::from_millis(50));
+            high.send(3).unwrap();
+        });
+        out.clear();
+        assert_eq!(mrx.receive_many(10, &mut out), 1);
+        assert_eq!(out, vec![3]);
+        sender.join().unwrap();
+    }
+
+    #[test]
+    fn receive_grouped() {
+        let mrx = DynMultiReceiver::<i32, u16>::new();
+        let a = mrx.new_channel(1, 10, false, None);
+        let b = mrx.new_channel(1, 20, false, None);
+        let c = mrx.new_channel(2, 10, false, None);
+        for x in 0..10 {
+            a.send(x).unwrap();
+            b.send(100 + x).unwrap();
+            c.send(200 + x).unwrap();
+        }
+
+        let first = mrx.receive_grouped(25);
+        assert_eq!(first.values().map(Vec::len).sum::<usize>(), 25);
+        assert_eq!(first[&a.id()], (0..10).collect::<Vec<_>>());
+        assert_eq!(first[&b.id()], (100..110).collect::<Vec<_>>());
+        assert_eq!(first[&c.id()], (200..205).collect::<Vec<_>>());
+
+        let rest = mrx.receive_grouped(100);
+        assert_eq!(rest.len(), 1);
+        assert_eq!(rest[&c.id()], (205..210).collect::<Vec<_>>());
+        assert!(mrx.receive_grouped(0).is_empty());
+        assert_eq!(mrx.pending_wakes(), 0);
+    }
+
+    #[test]
+    fn remove_channel_unblocks_senders() {
+        for bounds in [0, 1] {
+            let mrx = DynMultiReceiver::<i32, u16>::new();
+            let sender = mrx.new_channel(1, 10, false, Some(bounds));
+            let id = sender.id();
+            if bounds > 0 {
+                sender.send(0).unwrap();
+            }
+            let blocked = std::thread::spawn(move || sender.send(1));
+            std::thread::sleep(Duration::from_millis(50));
+            assert!(!blocked.is_finished());
+
+            let remover = mrx.clone();
+            std::thread::spawn(move || remover.remove_channel_by_id(id))
+                .join()
+                .unwrap();
+            let start = Instant::now();
+            while !blocked.is_finished() {
+                assert!(start.elapsed() < Duration::from_secs(1));
+                std::thread::sleep(Duration::from_millis(1));
+            }
+            assert!(matches!(
+                blocked.join().unwrap(),
+                Err(SendError::Disconnected)
+            ));
+        }
+    }
+
+    #[test]
+    fn starvation_report() {
+        let mrx = DynMultiReceiver::<i32, u16>::new().with_starvation_tracking();
+        let light = mrx.new_channel(1, 1, false, None);
+        let heavy = mrx.new_channel(1, 100, false, None);
+        for x in 0..200 {
+            light.send(x).unwrap();
+            heavy.send(x).unwrap();
+        }
+        for _ in 0..100 {
+            mrx.receive();
+        }
+        let report = mrx.starvation_report();
+        assert_eq!(report.len(), 2);
+        assert_eq!(report[0].0, light.id());
+        assert!(report[0].1 > 80);
+        assert!(report[1].1 < 20);
+
+        let untracked = DynMultiReceiver::<i32, u16>::new();
+        let a = untracked.new_channel(1, 1, false, None);
+        let b = untracked.new_channel(1, 1, false, None);
+        a.send(0).unwrap();
+        b.send(1).unwrap();
+        untracked.receive();
+        assert_eq!(
+            untracked.starvation_report(),
+            vec![(a.id(), 0), (b.id(), 0)]
+        );
+    }
+
+    #[test]
+    fn tier_defaults() {
+        let mrx = DynMultiReceiver::<i32, u16>::new()
+            .with_tier_defaults(|priority| (*priority as u32 * 10, *priority >= 5));
+        let low = mrx.new_channel(7, 10, false, None);
+        let high = mrx.new_channel(2, 10, false, None);
+        assert_eq!(mrx.tier_weight(&7), Some(70));
+        assert_eq!(mrx.tier_weight(&2), Some(20));
+        assert_eq!(mrx.tier_weight(&3), None);
+
+        let frozen = mrx.freeze_snapshot();
+        assert_eq!(frozen.get(low.id()), Some(true));
+        assert_eq!(frozen.get(high.id()), Some(false));
+
+        low.send(0).unwrap();
+        high.send(1).unwrap();
+        assert_eq!(mrx.receive(), 1);
+        low.set_frozen(false);
+        assert_eq!(mrx.receive(), 0);
+    }
+
+    #[test]
+    fn receive_with() {
+        let mrx = DynMultiReceiver::<i32, u16>::new();
+        let senders: Vec<_> = (1..4)
+            .map(|priority| mrx.new_channel(priority, 10, false, None))
+            .collect();
+        for (priority, sender) in (1..4).zip(&senders).rev() {
+            sender.send(priority * 100).unwrap();
+        }
+        for _ in 0..3 {
+            let (priority, value) = mrx.receive_with(|priority, value| (*priority, value));
+            assert_eq!(priority as i32 * 100, value);
+        }
+    }
+
+    #[test]
+    fn receive_matching() {
+        let mrx = DynMultiReceiver::<u32, u16>::new();
+        let high = mrx.new_channel(0, 1, false, None);
+        let low = mrx.new_channel(1, 1, false, None);
+        let rendezvous = mrx.new_channel(2, 1, false, Some(0));
+        // Even messages are control messages
+        for value in [11, 12, 14] {
+            low.send(value).unwrap();
+        }
+        for value in [1, 2, 3, 4] {
+            high.send(value).unwrap();
+        }
+        let is_control = |value: &u32| value.is_multiple_of(2);
+        // The heads 1 and 11 don't match, so both channels are skipped
+        let waiting = {
+            let mrx = mrx.clone();
+            std::thread::spawn(move || mrx.receive_matching(is_control))
+        };
+        std::thread::sleep(Duration::from_millis(50));
+        let sending = std::thread::spawn(move || rendezvous.send(20).unwrap());
+        assert_eq!(waiting.join().unwrap(), 20);
+        sending.join().unwrap();
+        assert_eq!(mrx.receive(), 1);
+        assert_eq!(mrx.receive_matching(is_control), 2);
+        assert_eq!(mrx.receive(), 3);
+        assert_eq!(mrx.receive_matching(is_control), 4);
+        assert_eq!(mrx.receive(), 11);
+        assert_eq!(mrx.receive_matching(is_control), 12);
+        assert_eq!(mrx.receive_matching(is_control), 14);
+    }
+
+    #[test]
+    fn receive_matching_panicking_pred() {
+        let mrx = DynMultiReceiver::<u32, u16>::new();
+        let sender = mrx.new_channel(0, 1, false, None);
+        sender.send_all([1, 2]).unwrap();
+        let panicked = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            mrx.receive_matching(|_| panic!("pred"))
+        }));
+        assert!(panicked.is_err());
+        // The peeked head stays in place and the channel keeps working
+        assert_eq!(mrx.recv(), Ok(1));
+        assert_eq!(mrx.receive_matching(|value| *value == 2), 2);
+        sender.send(3).unwrap();
+        assert_eq!(mrx.recv(), Ok(3));
+    }
+
+    #[test]
+    fn receive_created_before() {
+        let mrx = DynMultiReceiver::<i32, u16>::new();
+        let old = mrx.new_channel(2, 10, false, None);
+        let new = mrx.new_channel(1, 10, false, None);
+        let cutoff = new.id();
+        new.send(100).unwrap();
+        old.send(0).unwrap();
+        old.send(1).unwrap();
+        assert_eq!(mrx.receive_created_before(cutoff), 0);
+        assert_eq!(mrx.receive_created_before(cutoff), 1);
+
+        // Blocks despite the message in the new channel, until the old channel delivers
+        let mrx2 = mrx.clone();
+        let receiver = std::thread::spawn(move || mrx2.receive_created_before(cutoff));
+        std::thread::sleep(Duration::from_millis(50));
+        assert!(!receiver.is_finished());
+        old.send(2).unwrap();
+        assert_eq!(receiver.join().unwrap(), 2);
+        assert_eq!(mrx.receive(), 100);
+        assert_eq!(mrx.pending_wakes(), 0);
+    }
+
+    #[test]
+    fn in_flight_limit() {
+        let mrx = DynMultiReceiver::<i32, u16>::new().with_in_flight_limit(2);
+        let sender = mrx.new_channel(1, 10, false, None);
+        for x in 0..5 {
+            sender.send(x).unwrap();
+        }
+        let (first, ack) = mrx.receive_tracked();
+        let (second, _ack) = mrx.receive_tracked();
+        assert_eq!((first, second), (0, 1));
+        assert_eq!(mrx.in_flight(), 2);
+
+        // Clones are separate consumers
+        let other = mrx.clone();
+        let (value, other_ack) = other.receive_tracked();
+        assert_eq!(value, 2);
+        drop(other_ack);
+
+        std::thread::scope(|scope| {
+            let third = scope.spawn(|| mrx.receive_tracked().0);
+            std::thread::sleep(Duration::from_millis(50));
+            assert!(!third.is_finished());
+            ack.ack();
+            assert_eq!(third.join().unwrap(), 3);
+        });
+        // Only the second message is still in flight, the third Ack was dropped right away
+        assert_eq!(mrx.in_flight(), 1);
+    }
+
+    #[test]
+    fn receive_group_by() {
+        let mrx = DynMultiReceiver::<i32, u16>::new();
+        let a = mrx.new_channel(1, 10, false, None);
+        let b = mrx.new_channel(2, 10, false, None);
+        for x in 0..10 {
+            a.send(x).unwrap();
+            b.send(10 + x).unwrap();
+        }
+        let grouped = mrx.receive_group_by(|x| x % 3, 15);
+        assert_eq!(grouped.values().map(Vec::len).sum::<usize>(), 15);
+        assert_eq!(grouped[&0], vec![0, 3, 6, 9, 12]);
+        assert_eq!(grouped[&1], vec![1, 4, 7, 10, 13]);
+        assert_eq!(grouped[&2], vec![2, 5, 8, 11, 14]);
+        assert!(mrx.receive_group_by(|x| *x, 0).is_empty());
+    }
+
+    #[test]
+    fn deterministic_weighted() {
+        let mrx = DynMultiReceiver::<u32, u16>::new().with_policy(Policy::DeterministicWeighted);
+        let a = mrx.new_channel(1, 1, false, None);
+        let b = mrx.new_channel(1, 3, false, None);
+        for _ in 0..100 {
+            a.send(1).unwrap();
+            b.send(3).unwrap();
+        }
+        // Every window of four receives serves the exact weight ratio
+        for _ in 0..10 {
+            let mut window: Vec<_> = (0..4).map(|_| mrx.receive()).collect();
+            window.sort_unstable();
+            assert_eq!(window, vec![1, 3, 3, 3]);
+        }
+    }
+
+    #[test]
+    fn receive_strict() {
+        let mrx = DynMultiReceiver::<u32, u16>::new().with_size_fairness(|_| 1);
+        let senders: Vec<_> = (0..3)
+            .map(|i| mrx.new_channel(1, 1 + i * 10, false, None))
+            .collect();
+        let low = mrx.new_channel(2, 1, false, None);
+        low.send(3).unwrap();
+        for _ in 0..3 {
+            for (i, sender) in senders.iter().enumerate() {
+                sender.send(i as u32).unwrap();
+            }
+        }
+        let received: Vec<_> = (0..9).map(|_| mrx.receive_strict()).collect();
+        assert_eq!(received, vec![0, 1, 2, 0, 1, 2, 0, 1, 2]);
+        // Channels without a message are skipped
+        senders[0].send(0).unwrap();
+        senders[2].send(2).unwrap();
+        assert_eq!(mrx.receive_strict(), 0);
+        assert_eq!(mrx.receive_strict(), 2);
+        assert_eq!(mrx.receive_strict(), 3);
+    }
+
+    #[test]
+    fn many_failed_candidates() {
+        // Every candidate but the last turns out to be empty when taking, so each one is dropped in turn
+        for policy in [
+            Policy::RoundRobin,
+            Policy::Weighted,
+            Policy::DeterministicWeighted,
+        ] {
+            let mrx = DynMultiReceiver::<u32, u16>::new().with_policy(policy);
+            let senders: Vec<_> = (0..20_000)
+                .map(|_| mrx.new_channel(1, 1, false, None))
+                .collect();
+            for sender in &senders {
+                sender.send(sender.id()).unwrap();
+            }
+            let last = senders.last().unwrap().id();
+            let mut rng = SmallRng::seed_from_u64(5);
+            let mut scratch = ReceiveScratch::new();
+            for _ in 0..10 {
+                let state = mrx.state.read().unwrap();
+                let selection = mrx
+                    .select_with(
+                        &state,
+                        &mut rng,
+                        |_| true,
+                        |receiver| match receiver.id == last {
+                            true => Ok(last),
+                            false => Err(crossbeam_channel::TryRecvError::Empty),
+                        },
+                        &mut scratch,
+                        None,
+                    )
+                    .unwrap();
+                assert!(matches!(selection, Selection::Found(selected) if selected.value == last));
+            }
+        }
+    }
+
+    #[test]
+    fn huge_weights() {
+        // The weights of a group add up far beyond u32::MAX
+        for policy in [Policy::Weighted, Policy::DeterministicWeighted] {
+            let mrx = DynMultiReceiver::<u32, u16>::new().with_policy(policy);
+            let senders: Vec<_> = (0..500)
+                .map(|i| mrx.new_channel(1, u32::MAX - i, false, None))
+                .collect();
+            for sender in &senders {
+                sender.send_all([sender.id(); 4]).unwrap();
+            }
+            let mut received: Vec<_> = (0..2000).map(|_| mrx.receive()).collect();
+            received.sort_unstable();
+            assert_eq!(
+                received,
+                (0..500).flat_map(|id| [id; 4]).collect::<Vec<_>>()
+            );
+        }
+    }
+
+    #[test]
+    fn zero_weights() {
+        for policy in [Policy::Weighted, Policy::DeterministicWeighted] {
+            let mrx = DynMultiReceiver::<u32, u16>::new().with_policy(policy);
+            let senders: Vec<_> = (0..3).map(|_| mrx.new_channel(1, 1, false, None)).collect();
+            let weighted = mrx.new_channel(1, 1, false, None);
+            // Weights can't be set to 0 through the API
+            for receiver in &mut mrx.state.write().unwrap().groups[0].receivers {
+                if receiver.id != weighted.id() {
+                    receiver.weight = 0;
+                }
+            }
+            for sender in &senders {
+                sender.send_all([sender.id(); 10]).unwrap();
+            }
+            weighted.send_all([weighted.id(); 5]).unwrap();
+            // Channels with a weight are served first, then the others in turn
+            let received: Vec<_> = (0..35).map(|_| mrx.receive()).collect();
+            assert_eq!(received[..5], [weighted.id(); 5]);
+            assert_eq!(received[5..], [0, 1, 2].repeat(10));
+        }
+    }
+
+    #[test]
+    fn aging() {
+        let mrx = DynMultiReceiver::<u16, u16>::new();
+        let high = mrx.new_channel(1, 1, false, None);
+        let mid = mrx.new_channel(2, 1, false, None);
+        let low = mrx.new_channel(3, 1, false, None);
+        for _ in 0..100 {
+            high.send(1).unwrap();
+            mid.send(2).unwrap();
+            low.send(3).unwrap();
+        }
+        // Without aging, the high priority channel is drained first
+        assert!((0..10).all(|_| mrx.receive() == 1));
+
+        // Every fourth receive goes to the highest tier below with messages
+        mrx.set_aging(3);
+        let received: Vec<_> = (0..40).map(|_| mrx.receive()).collect();
+        assert_eq!(received, [1, 1, 1, 2].repeat(10));
+
+        // Without a lower tier to yield to, the top tier keeps being served
+        while mrx.try_recv_from(mid.id()).is_ok() {}
+        while mrx.try_recv_from(low.id()).is_ok() {}
+        assert!((0..8).all(|_| mrx.receive() == 1));
+
+        mrx.set_aging(0);
+        low.send(3).unwrap();
+        assert!((0..10).all(|_| mrx.receive() == 1));
+    }
+
+    #[test]
+    fn set_group_policy() {
+        let mrx = DynMultiReceiver::<u32, u16>::new();
+        mrx.set_group_policy(1, Policy::RoundRobin);
+        let round_robin = |senders: &[crate::DynSender<u32, u16>]| {
+            for _ in 0..4 {
+                for (i, sender) in senders.iter().enumerate() {
+                    sender.send(i as u32).unwrap();
+                }
+            }
+            let received: Vec<_> = (0..senders.len() * 4).map(|_| mrx.receive()).collect();
+            let expected: Vec<_> = (0..4).flat_map(|_| 0..senders.len() as u32).collect();
+            assert_eq!(received, expected);
+        };
+        let senders: Vec<_> = (0..3)
+            .map(|i| mrx.new_channel(1, 1 + i * 100, false, None))
+            .collect();
+        round_robin(&senders);
+        // The policy is kept while the tier has no channels
+        for sender in &senders {
+            mrx.remove_channel(sender);
+        }
+        let senders: Vec<_> = (0..4)
+            .map(|i| mrx.new_channel(1, 1 + i * 100, false, None))
+            .collect();
+        round_robin(&senders);
+        // And moves with its tier
+        mrx.remap_priorities(|priority| priority + 1);
+        round_robin(&senders);
+        // Other tiers keep sampling by weight
+        let weighted: Vec<_> = (0..2)
+            .map(|i| mrx.new_channel(1, 1 + i * 1000, false, None))
+            .collect();
+        for _ in 0..100 {
+            weighted[0].send(0).unwrap();
+            weighted[1].send(1).unwrap();
+        }
+        let heavy = (0..100).filter(|_| mrx.receive() == 1).count();
+        assert!(heavy > 90);
+    }
+
+    #[test]
+    fn receive_with_scratch() {
+        // Deterministic selection makes both receivers serve the exact same sequence
+        let plain = DynMultiReceiver::<u32, u16>::new().with_policy(Policy::DeterministicWeighted);
+        let reused = DynMultiReceiver::<u32, u16>::new().with_policy(Policy::DeterministicWeighted);
+        let mut senders = Vec::new();
+        // More ready channels per group than the inline scratch capacity
+        for i in 0..20u32 {
+            let priority = (i % 2) as u16;
+            senders.push(plain.new_channel(priority, i + 1, false, None));
+            senders.push(reused.new_channel(priority, i + 1, false, None));
+        }
+        for round in 0..50 {
+            for sender in &senders {
+                sender.send(sender.id() * 1000 + round).unwrap();
+            }
+        }
+        let mut scratch = ReceiveScratch::new();
+        for _ in 0..1000 {
+            assert_eq!(reused.receive_with_scratch(&mut scratch), plain.receive());
+        }
+    }
+
+    #[test]
+    fn without_freezing() {
+        let mrx = DynMultiReceiver::<u32, u16>::new().without_freezing();
+        let high = mrx.new_channel(0, 1, false, None);
+        let low = mrx.new_channel(1, 1, false, None);
+        low.send(1).unwrap();
+        high.send(0).unwrap();
+        assert_eq!(mrx.receive(), 0);
+        assert_eq!(mrx.receive(), 1);
+        // Unfreezing is a no-op and still allowed
+        high.set_frozen(false);
+
+        let frozen_sender =
+            std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| high.set_frozen(true)));
+        assert!(frozen_sender.is_err());
+        let frozen_channel = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            mrx.new_channel(2, 1, true, None);
+        }));
+        assert!(frozen_channel.is_err());
+        let frozen_many = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            mrx.set_frozen_many([low.id()], true)
+        }));
+        assert!(frozen_many.is_err());
+        // The rejected calls leave the multichannel usable
+        low.send(2).unwrap();
+        assert_eq!(mrx.receive(), 2);
+    }
+
+    #[test]
+    fn with_capacity() {
+        let mrx = DynMultiReceiver::<u32, u16>::new().with_capacity(10, 1000);
+        let (groups, lookup) = {
+            let state = mrx.state.read().unwrap();
+            (state.groups.as_ptr(), state.lookup.capacity())
+        };
+        assert!(lookup >= 1000);
+        let senders: Vec<_> = (0..1000)
+            .map(|i| mrx.new_channel(i % 10, 1, false, None))
+            .collect();
+        {
+            let state = mrx.state.read().unwrap();
+            assert_eq!(state.groups.as_ptr(), groups);
+            assert_eq!(state.lookup.capacity(), lookup);
+        }
+        for sender in &senders {
+            sender.send(sender.id()).unwrap();
+        }
+        let mut received: Vec<_> = (0..1000).map(|_| mrx.receive()).collect();
+        received.sort_unstable();
+        assert_eq!(received, (0..1000).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn empty_behavior_block() {
+        let mrx = DynMultiReceiver::<u32, u16>::new().with_empty_behavior(EmptyBehavior::Block);
+        let consumer = mrx.clone();
+        let receiving = std::thread::spawn(move || consumer.receive_or_none());
+        std::thread::sleep(Duration::from_millis(50));
+        assert!(!receiving.is_finished());
+        mrx.new_channel(0, 1, false, None).send(1).unwrap();
+        assert_eq!(receiving.join().unwrap(), Some(1));
+    }
+
+    #[test]
+    fn empty_behavior_panic() {
+        let mrx = DynMultiReceiver::<u32, u16>::new().with_empty_behavior(EmptyBehavior::Panic);
+        let consumer = mrx.clone();
+        assert!(std::thread::spawn(move || consumer.receive())
+            .join()
+            .is_err());
+        let sender = mrx.new_channel(0, 1, false, None);
+        sender.send(1).unwrap();
+        assert_eq!(mrx.receive(), 1);
+        // Also when the last channel goes away while waiting
+        let consumer = mrx.clone();
+        let receiving = std::thread::spawn(move || consumer.receive());
+        std::thread::sleep(Duration::from_millis(50));
+        mrx.remove_channel(&sender);
+        assert!(receiving.join().is_err());
+    }
+
+    #[test]
+    fn empty_behavior_return_none() {
+        let mrx =
+            DynMultiReceiver::<u32, u16>::new().with_empty_behavior(EmptyBehavior::ReturnNone);
+        assert_eq!(mrx.receive_or_none(), None);
+        let sender = mrx.new_channel(0, 1, false, None);
+        sender.send(1).unwrap();
+        assert_eq!(mrx.receive_or_none(), Some(1));
+        let consumer = mrx.clone();
+        let receiving = std::thread::spawn(move || consumer.receive_or_none());
+        std::thread::sleep(Duration::from_millis(50));
+        drop(sender);
+        assert_eq!(receiving.join().unwrap(), None);
+        // receive() has nothing to return
+        let consumer = mrx.clone();
+        assert!(std::thread::spawn(move || consumer.receive())
+            .join()
+            .is_err());
+    }
+
+    #[test]
+    fn group_len() {
+        let mrx = DynMultiReceiver::<u32, u16>::new();
+        let high = mrx.new_channel(0, 1, false, None);
+        let low = mrx.new_channel(1, 1, false, None);
+        let frozen = mrx.new_channel(1, 1, true, None);
+        high.send_all([0, 1]).unwrap();
+        low.send_all([2, 3, 4]).unwrap();
+        frozen.send(5).unwrap();
+        assert_eq!(mrx.group_len(&0), 2);
+        assert_eq!(mrx.group_len(&1), 4);
+        assert_eq!(mrx.group_len(&7), 0);
+        mrx.receive();
+        assert_eq!(mrx.group_len(&0), 1);
+        assert_eq!(mrx.group_len(&1), 4);
+    }
+
+    #[test]
+    fn receive_ref() {
+        let mrx = DynMultiReceiver::<Vec<u8>, u16>::new();
+        let low = mrx.new_channel(1, 1, false, None);
+        let high = mrx.new_channel(0, 1, false, None);
+        low.send(vec![1; 1024]).unwrap();
+        high.send(vec![0; 4096]).unwrap();
+        assert_eq!(
+            mrx.receive_ref(|message| (message[0], message.len())),
+            (0, 4096)
+        );
+        assert_eq!(
+            mrx.receive_ref(|message| (message[0], message.len())),
+            (1, 1024)
+        );
+        assert!(mrx.is_empty());
+    }
+
+    #[test]
+    fn raw_receiver() {
+        let mrx = DynMultiReceiver::<u32, u16>::new();
+        let sender = mrx.new_channel(1, 1, false, None);
+        let other = mrx.new_channel(0, 1, false, None);
+        assert!(mrx.raw_receiver(99).is_none());
+        let raw = mrx.raw_receiver(sender.id()).unwrap();
+        sender.send_all([1, 2, 3]).unwrap();
+        assert_eq!(raw.len(), 3);
+        assert_eq!(raw.try_recv(), Ok(1));
+        // Both see the same buffer
+        assert_eq!(mrx.receive(), 2);
+        assert_eq!(mrx.len(), 1);
+        assert_eq!(mrx.stats(sender.id()).unwrap().received, 2);
+
+        // The raw receiver works in a select loop, even with higher priority messages waiting
+        other.send(0).unwrap();
+        let mut select = crossbeam_channel::Select::new();
+        let index = raw.register(&mut select);
+        let operation = select.select();
+        assert_eq!(operation.index(), index);
+        assert_eq!(raw.complete(operation), Ok(3));
+        assert!(raw.is_empty());
+        assert_eq!(raw.try_recv(), Err(crossbeam_channel::TryRecvError::Empty));
+        assert_eq!(mrx.receive(), 0);
+        assert_eq!(mrx.total_received(), 4);
+    }
+
+    #[test]
+    fn round_robin_shared_by_consumers() {
+        // Consumers on separate clones share the rotation, so together they serve every channel equally often
+        let mrx = DynMultiReceiver::<u32, u16>::new().with_policy(Policy::RoundRobin);
+        let senders: Vec<_> = (0..8).map(|_| mrx.new_channel(0, 1, false, None)).collect();
+        for sender in &senders {
+            sender.send_all([sender.id(); 1000]).unwrap();
+        }
+        let counts: Vec<_> = (0..8).map(|_| AtomicUsize::new(0)).collect();
+        std::thread::scope(|scope| {
+            for _ in 0..4 {
+                let consumer = mrx.clone();
+                let counts = &counts;
+                scope.spawn(move || {
+                    for _ in 0..1000 {
+                        counts[consumer.receive() as usize].fetch_add(1, Ordering::Relaxed);
+                    }
+                });
+            }
+        });
+        for count in &counts {
+            let count = count.load(Ordering::Relaxed);
+            assert!((495..=505).contains(&count), "{count}");
+        }
+    }
+
+    #[test]
+    fn receive_from_priority_at_least() {
+        let mrx = DynMultiReceiver::<u32, u16>::new();
+        let urgent = mrx.new_channel(0, 1, false, None);
+        let normal = mrx.new_channel(5, 1, false, None);
+        let background = mrx.new_channel(9, 1, false, None);
+        background.send_all([90, 91]).unwrap();
+        // Only less urgent messages are buffered
+        assert_eq!(mrx.receive_from_priority_at_least(5), None);
+        normal.send(50).unwrap();
+        urgent.send(0).unwrap();
+        assert_eq!(mrx.receive_from_priority_at_least(5), Some(0));
+        assert_eq!(mrx.receive_from_priority_at_least(5), Some(50));
+        assert_eq!(mrx.receive_from_priority_at_least(5), None);
+        // A channel moved above the floor counts with its new priority
+        background.set_priority(1);
+        assert_eq!(mrx.receive_from_priority_at_least(5), Some(90));
+        assert_eq!(mrx.receive(), 91);
+    }
+
+    #[test]
+    fn events() {
+        let mrx = DynMultiReceiver::<u32, u16>::new();
+        let before = mrx.new_channel(0, 1, false, None);
+        let events = mrx.events();
+        let other_events = mrx.events();
+        let a = mrx.new_channel(3, 1, false, None);
+        let b = mrx.new_channel(1, 1, false, None);
+        // Moving a channel is no event
+        b.set_priority(2);
+        mrx.remove_channel(&a);
+        drop(other_events);
+        // The channel of a dropped sender is removed as well
+        drop(b);
+        mrx.reap_disconnected();
+        drop(before);
+        mrx.reap_disconnected();
+        let expected = [
+            ChannelEvent::Added { id: 1, priority: 3 },
+            ChannelEvent::Added { id: 2, priority: 1 },
+            ChannelEvent::Removed { id: 1 },
+            ChannelEvent::Removed { id: 2 },
+            ChannelEvent::Removed { id: 0 },
+        ];
+        assert_eq!(events.try_iter().collect::<Vec<_>>(), expected);
+        // The dropped subscriber was unregistered
+        assert_eq!(mrx.state.read().unwrap().subscribers.len(), 1);
+    }
+
+    #[test]
+    fn named_channels() {
+        let mrx = DynMultiReceiver::<u32, u16>::new();
+        let control = mrx.new_named_channel("control", 0, 1, false, None).unwrap();
+        let data = mrx.new_named_channel("data", 1, 1, false, None).unwrap();
+        let unnamed = mrx.new_channel(1, 1, false, None);
+        assert!(matches!(
+            mrx.new_named_channel("data", 2, 1, false, None),
+            Err(NameTakenError(name)) if name == "data"
+        ));
+        assert_eq!(mrx.channel_id_by_name("control"), Some(control.id()));
+        assert_eq!(mrx.channel_id_by_name("data"), Some(data.id()));
+        assert_eq!(mrx.channel_id_by_name("missing"), None);
+
+        // Moving a channel to another priority keeps its name
+        data.set_priority(2);
+        assert_eq!(mrx.channel_id_by_name("data"), Some(data.id()));
+
+        assert!(mrx.remove_channel_by_name("data"));
+        assert!(!mrx.remove_channel_by_name("data"));
+        assert_eq!(mrx.channel_id_by_name("data"), None);
+        assert!(matches!(data.send(1), Err(SendError::Disconnected)));
+        // The name is free again
+        let data = mrx.new_named_channel("data", 1, 1, false, None).unwrap();
+        assert_eq!(mrx.channel_id_by_name("data"), Some(data.id()));
+
+        // Removing a channel by other means releases its name as well
+        mrx.remove_channel(&control);
+        assert_eq!(mrx.channel_id_by_name("control"), None);
+        unnamed.send(2).unwrap();
+        assert_eq!(mrx.receive(), 2);
+    }
+
+    #[test]
+    fn shrink_to_fit() {
+        let mrx = DynMultiReceiver::<u32, u16>::new();
+        let keep = mrx.new_channel(0, 1, false, None);
+        let senders: Vec<_> = (0..5000)
+            .map(|i| mrx.new_channel(i % 100, 1, false, None))
+            .collect();
+        for sender in &senders {
+            mrx.remove_channel(sender);
+        }
+        let capacity = |mrx: &DynMultiReceiver<u32, u16>| {
+            let state = mrx.state.read().unwrap();
+            (
+                state.groups.capacity(),
+                state.groups[0].receivers.capacity(),
+                state.groups[0].slots.len(),
+                state.lookup.capacity(),
+            )
+        };
+        let (groups, receivers, slots, lookup) = capacity(&mrx);
+        assert!(groups >= 100 && receivers >= 50 && slots >= 50 && lookup >= 5000);
+        mrx.shrink_to_fit();
+        let (groups, receivers, slots, lookup) = capacity(&mrx);
+        assert!(groups < 100 && receivers < 50 && slots < 50 && lookup < 5000);
+        // The remaining channel and new ones still work
+        let other = mrx.new_channel(0, 1, false, None);
+        keep.send(1).unwrap();
+        other.send(2).unwrap();
+        let mut received = [mrx.receive(), mrx.receive()];
+        received.sort_unstable();
+        assert_eq!(received, [1, 2]);
+    }
+
+    #[test]
+    fn many_distinct_priorities() {
+        let mrx = DynMultiReceiver::<u32, u32>::new();
+        // Every new tier is inserted in front of the others
+        let mut senders: Vec<_> = (0..3000)
+            .rev()
+            .flat_map(|tier| {
+                [
+                    mrx.new_channel(tier, 1, false, None),
+                    mrx.new_channel(tier, 1, false, None),
+                ]
+            })
+            .collect();
+        // Removing tiers in the middle and moving channels into new ones keeps every channel reachable by its id
+        for sender in senders.drain(1000..2000) {
+            mrx.remove_channel(&sender);
+        }
+        for sender in &senders[..100] {
+            sender.set_priority(10_000 + sender.id());
+        }
+        for sender in &senders {
+            sender.send(sender.id()).unwrap();
+        }
+        mrx.set_frozen_many(senders.iter().map(|s| s.id()), true);
+        assert!(senders.iter().all(|s| s.is_frozen()));
+        mrx.unfreeze_all();
+        for _ in 0..senders.len() {
+            let (id, value) = mrx.receive_with_id();
+            assert_eq!(id, value);
+        }
+        assert_eq!(mrx.try_receive(), Err(TryReceiveError::Empty));
+    }
+
+    #[test]
+    fn ready_tiers() {
+        let mrx = DynMultiReceiver::<u16, u16>::new();
+        let mut senders = (0..300)
+            .map(|tier| (tier * 2, mrx.new_channel(tier * 2, 1, false, None)))
+            .collect::<Vec<_>>();
+        let frozen = mrx.new_channel(0, 1, true, None);
+        frozen.send(u16::MAX).unwrap();
+        let mut rng = SmallRng::seed_from_u64(3);
+        let mut drain_in_order = |senders: &[(u16, crate::DynSender<u16, u16>)],
+                                  tier: fn(u16) -> u16| {
+            let mut sent = Vec::new();
+            for _ in 0..200 {
+                let (priority, sender) = senders.choose(&mut rng).unwrap();
+                sender.send(*priority).unwrap();
+                sent.push(*priority);
+            }
+            let mut received = (0..sent.len())
+                .map(|_| mrx.try_receive().unwrap())
+                .collect::<Vec<_>>();
+            assert!(received.windows(2).all(|w| tier(w[0]) <= tier(w[1])));
+            assert_eq!(mrx.try_receive(), Err(TryReceiveError::Empty));
+            sent.sort_unstable();
+            received.sort_unstable();
+            assert_eq!(received, sent);
+        };
+        drain_in_order(&senders, |priority| priority);
+        // Removing and inserting tiers moves the tier bits of the following tiers
+        for (_, sender) in senders.drain(100..150) {
+            mrx.remove_channel(&sender);
+        }
+        drain_in_order(&senders, |priority| priority);
+        senders.extend(
+            (0..50).map(|tier| (tier * 4 + 1, mrx.new_channel(tier * 4 + 1, 1, false, None))),
+        );
+        drain_in_order(&senders, |priority| priority);
+        // Merging tiers as well
+        mrx.remap_priorities(|priority| priority / 3);
+        drain_in_order(&senders, |priority| priority / 3);
+        frozen.set_frozen(false);
+        assert_eq!(mrx.receive(), u16::MAX);
+    }
+
+    #[test]
+    fn ready_tiers_move_with_messages() {
+        // Tiers are inserted and removed while others hold messages, so their set bits have to move along
+        let mrx = DynMultiReceiver::<u16, u16>::new();
+        let mut senders = (0..200)
+            .rev()
+            .map(|tier| (tier * 2, mrx.new_channel(tier * 2, 1, false, None)))
+            .collect::<Vec<_>>();
+        for (priority, sender) in senders.iter().step_by(3) {
+            sender.send(*priority).unwrap();
+        }
+        for (_, sender) in senders
+            .drain(50..120)
+            .filter(|(priority, _)| priority % 3 != 0)
+        {
+            mrx.remove_channel(&sender);
+        }
+        for tier in (0..200).rev() {
+            let sender = mrx.new_channel(tier * 2 + 1, 1, false, None);
+            if tier % 5 == 0 {
+                sender.send(tier * 2 + 1).unwrap();
+            }
+            senders.push((tier * 2 + 1, sender));
+        }
+        let mut expected = senders
+            .iter()
+            .map(|(priority, sender)| (*priority, sender.len()))
+            .filter(|(_, len)| *len > 0)
+            .map(|(priority, _)| priority)
+            .collect::<Vec<_>>();
+        expected.sort_unstable();
+        let received = (0..expected.len())
+            .map(|_| mrx.try_receive().unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(received, expected);
+        assert_eq!(mrx.try_receive(), Err(TryReceiveError::Empty));
+    }
+
+    #[test]
+    fn bridge_to() {
+        let source = DynMultiReceiver::<u32, u16>::new();
+        let target = DynMultiReceiver::<u32, u16>::new();
+        let senders: Vec<_> = (0..3)
+            .map(|priority| source.new_channel(priority, 1, false, None))
+            .collect();
+        let bridge = source.bridge_to(&target, |x| (*x % 4) as u16, 1);
+        for x in 0..100 {
+            senders[x as usize % 3].send(x).unwrap();
+        }
+        let mut received = Vec::new();
+        for _ in 0..100 {
+            let (priority, x) = target.receive_with(|priority, x| (*priority, x));
+            assert_eq!(priority, (x % 4) as u16);
+            received.push(x);
+        }
+        received.sort_unstable();
+        assert_eq!(received, (0..100).collect::<Vec<_>>());
+
+        // Removing the channels of the bridge from the target shuts it down with the next message
+        assert_eq!(target.reap_idle(Duration::ZERO).len(), 4);
+        senders[0].send(0).unwrap();
+        bridge.join().unwrap();
+        assert!(target.no_channels());
+    }
+
+    #[test]
+    fn remap_priorities() {
+        let mrx = DynMultiReceiver::<u32, u16>::new();
+        let high = mrx.new_channel(0, 1, false, None);
+        let low = mrx.new_channel(1, 1, false, None);
+        low.send(10).unwrap();
+        high.send(0).unwrap();
+        // Reverse the order and make room for a tier in between
+        mrx.remap_priorities(|p| if *p == 0 { 2 } else { 0 });
+        let middle = mrx.new_channel(1, 1, false, None);
+        middle.send(5).unwrap();
+        // Senders keep working after their channel moved
+        low.send(11).unwrap();
+        high.send(1).unwrap();
+        let received: Vec<_> = (0..5).map(|_| mrx.receive()).collect();
+        assert_eq!(received, vec![10, 11, 5, 0, 1]);
+
+        // Merging all tiers preserves every buffered message
+        for x in 0..30 {
+            [&high, &middle, &low][x as usize % 3].send(x).unwrap();
+        }
+        mrx.remap_priorities(|_| 7);
+        assert_eq!(mrx.tier_weight(&7), Some(1));
+        assert_eq!(mrx.tier_weight(&0), None);
+        let mut received: Vec<_> = (0..30).map(|_| mrx.receive()).collect();
+        received.sort_unstable();
+        assert_eq!(received, (0..30).collect::<Vec<_>>());
+        high.send(42).unwrap();
+        assert_eq!(mrx.receive(), 42);
+    }
+
+    #[test]
+    fn receivable_priorities() {
+        let mrx = DynMultiReceiver::<u32, u16>::new();
+        let frozen = mrx.new_channel(0, 1, true, None);
+        let _empty = mrx.new_channel(1, 1, false, None);
+        let low = mrx.new_channel(3, 1, false, None);
+        let mixed_frozen = mrx.new_channel(2, 1, true, None);
+        let mixed = mrx.new_channel(2, 1, false, None);
+        assert!(mrx.receivable_priorities().is_empty());
+
+        frozen.send(0).unwrap();
+        mixed_frozen.send(2).unwrap();
+        low.send(3).unwrap();
+        assert_eq!(mrx.receivable_priorities(), vec![3]);
+        mixed.send(2).unwrap();
+        assert_eq!(mrx.receivable_priorities(), vec![2, 3]);
+        frozen.set_frozen(false);
+        assert_eq!(mrx.receivable_priorities(), vec![0, 2, 3]);
+    }
+
+    #[test]
+    fn try_receive() {
+        let mrx = DynMultiReceiver::<u32, u16>::new();
+        assert_eq!(mrx.try_receive(), Err(TryReceiveError::Empty));
+        let a = mrx.new_channel(0, 1, true, None);
+        let b = mrx.new_channel(1, 1, true, None);
+        a.send(0).unwrap();
+        b.send(1).unwrap();
+        // Every channel with a message is frozen, which leaves the wake tokens alone
+        let wakes = mrx.pending_wakes();
+        assert_eq!(mrx.try_receive(), Err(TryReceiveError::Empty));
+        assert_eq!(mrx.try_receive(), Err(TryReceiveError::Empty));
+        assert_eq!(mrx.pending_wakes(), wakes);
+        b.set_frozen(false);
+        assert_eq!(mrx.try_receive(), Ok(1));
+        assert_eq!(mrx.try_receive(), Err(TryReceiveError::Empty));
+        a.set_frozen(false);
+        assert_eq!(mrx.receive(), 0);
+    }
+
+    #[test]
+    fn receive_timeout() {
+        let mrx = DynMultiReceiver::<u32, u16>::new();
+        let timeout = Duration::from_millis(50);
+        assert_eq!(
+            mrx.receive_timeout(timeout),
+            Err(RecvTimeoutError::Disconnected)
+        );
+        let frozen = mrx.new_channel(0, 1, true, None);
+        let _idle = mrx.new_channel(1, 1, false, None);
+        frozen.send(0).unwrap();
+        // Only a frozen message is buffered, so the timeout has to fire
+        let start = Instant::now();
+        assert_eq!(mrx.receive_timeout(timeout), Err(RecvTimeoutError::Timeout));
+        let elapsed = start.elapsed();
+        assert!(
+            elapsed >= timeout && elapsed < timeout * 20,
+            "{:?}",
+            elapsed
+        );
+
+        // A message sent while waiting is delivered right away
+        let sender = std::thread::spawn(move || {
+            std::thread::sleep(Duration::from_millis(20));
+            frozen.set_frozen(false);
+            frozen
+        });
+        assert_eq!(mrx.receive_timeout(Duration::from_secs(10)), Ok(0));
+        let frozen = sender.join().unwrap();
+        frozen.send(1).unwrap();
+        assert_eq!(mrx.receive(), 1);
+    }
+
+    /// CPU time used by the calling thread so far, in clock ticks.
+    #[cfg(target_os = "linux")]
+    fn thread_cpu_ticks() -> u64 {
+        let stat = std::fs::read_to_string("/proc/thread-self/stat").unwrap();
+        // The fields after the parenthesized command start with the state, utime and stime are the 14th and 15th
+        let fields: Vec<&str> = stat
+            .rsplit_once(')')
+            .unwrap()
+            .1
+            .split_whitespace()
+            .collect();
+        fields[11].parse::<u64>().unwrap() + fields[12].parse::<u64>().unwrap()
+    }
+
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn receive_blocks_on_frozen() {
+        type Receive = fn(&DynMultiReceiver<u32, u16>) -> u32;
+        let receives: [Receive; 3] = [
+            |mrx| mrx.receive(),
+            |mrx| mrx.receive_oldest(),
+            |mrx| mrx.receive_coalesced(|_, b| b),
+        ];
+        for receive in receives {
+            let mrx = DynMultiReceiver::<u32, u16>::new();
+            let sender = mrx.new_channel(0, 1, true, None);
+            sender.send(1).unwrap();
+            let receiver = {
+                let mrx = mrx.clone();
+                std::thread::spawn(move || {
+                    let start = thread_cpu_ticks();
+                    let value = receive(&mrx);
+                    (value, thread_cpu_ticks() - start)
+                })
+            };
+            // The receiver must wait without holding the lock, or unfreezing couldn't proceed
+            std::thread::sleep(Duration::from_millis(300));
+            sender.set_frozen(false);
+            let (value, ticks) = receiver.join().unwrap();
+            assert_eq!(value, 1);
+            // Spinning for 300ms would take about 30 ticks
+            assert!(ticks < 10, "receiver used {} ticks while waiting", ticks);
+        }
+    }
+
+    #[test]
+    fn peek_priority() {
+        let mrx = DynMultiReceiver::<u32, u16>::new();
+        assert_eq!(mrx.peek_priority(), None);
+        let frozen = mrx.new_channel(0, 1, true, None);
+        let _empty = mrx.new_channel(1, 1, false, None);
+        let low = mrx.new_channel(3, 1, false, None);
+        frozen.send(0).unwrap();
+        assert_eq!(mrx.peek_priority(), None);
+        low.send(3).unwrap();
+        // Peeking doesn't take the message
+        assert_eq!(mrx.peek_priority(), Some(3));
+        assert_eq!(mrx.peek_priority(), Some(3));
+        frozen.set_frozen(false);
+        assert_eq!(mrx.peek_priority(), Some(0));
+        assert_eq!(mrx.receive(), 0);
+        assert_eq!(mrx.peek_priority(), Some(3));
+        assert_eq!(mrx.receive(), 3);
+        assert_eq!(mrx.peek_priority(), None);
+    }
+
+    #[test]
+    fn len() {
+        let mrx = DynMultiReceiver::<u32, u16>::new();
+        assert!(mrx.is_empty());
+        let a = mrx.new_channel(0, 1, false, None);
+        let frozen = mrx.new_channel(1, 1, true, Some(50));
+        assert!(mrx.is_empty());
+        assert!(!mrx.no_channels());
+        for x in 0..30 {
+            a.send(x).unwrap();
+            frozen.send(x).unwrap();
+        }
+        assert_eq!(mrx.len(), 60);
+        assert!(!mrx.is_empty());
+        for n in (30..60).rev() {
+            mrx.receive();
+            assert_eq!(mrx.len(), n);
+        }
+        // Only the frozen messages are left
+        assert!(!mrx.is_empty());
+    }
+
+    #[test]
+    fn sender_len() {
+        let mrx = DynMultiReceiver::<u32, u16>::new();
+        let a = mrx.new_channel(0, 1, false, Some(10));
+        let b = mrx.new_channel(1, 1, false, None);
+        let rendezvous = mrx.new_channel(2, 1, false, Some(0));
+        for x in 0..5 {
+            a.send(x).unwrap();
+        }
+        b.send(5).unwrap();
+        assert_eq!(a.len(), 5);
+        assert_eq!(b.len(), 1);
+        assert!(rendezvous.is_empty());
+        mrx.receive();
+        assert_eq!(a.len(), 4);
+        assert_eq!(b.len(), 1);
+    }
+
+    #[test]
+    fn set_weight() {
+        let mrx = DynMultiReceiver::<u32, u16>::new();
+        let a = mrx.new_channel(0, 1, false, None);
+        let b = mrx.new_channel(0, 1, false, None);
+        for _ in 0..4000 {
+            a.send(0).unwrap();
+            b.send(1).unwrap();
+        }
+        let mut rng = SmallRng::seed_from_u64(7);
+        let mut share_of_a = |mrx: &DynMultiReceiver<u32, u16>| {
+            let from_a = (0..2000)
+                .filter(|_| mrx.receive_with_rng(&mut rng) == 0)
+                .count();
+            from_a as f64 / 2000.0
+        };
+        let before = share_of_a(&mrx);
+        assert!((before - 0.5).abs() < 0.05, "{}", before);
+        a.set_weight(9);
+        let after = share_of_a(&mrx);
+        assert!((after - 0.9).abs() < 0.05, "{}", after);
+    }
+
+    #[test]
+    fn set_priority() {
+        let mrx = DynMultiReceiver::<u32, u16>::new();
+        let high = mrx.new_channel(0, 1, false, None);
+        let low = mrx.new_channel(2, 1, false, None);
+        let other_low = mrx.new_channel(2, 1, false, None);
+        for x in 0..5 {
+            low.send(x).unwrap();
+            other_low.send(100 + x).unwrap();
+        }
+        high.send(1000).unwrap();
+        // Raised above the other low priority traffic, but still below high
+        low.set_priority(1);
+        assert_eq!(mrx.tier_weight(&1), Some(1));
+        let received: Vec<_> = (0..6).map(|_| mrx.receive()).collect();
+        assert_eq!(received, vec![1000, 0, 1, 2, 3, 4]);
+        // The sender keeps working at the new priority
+        other_low.send(105).unwrap();
+        low.send(5).unwrap();
+        assert_eq!(mrx.receive(), 5);
+        // Moving the only channel of a tier away removes the tier
+        high.set_priority(2);
+        assert_eq!(mrx.tier_weight(&0), None);
+        mrx.remove_channel(&high);
+        high.set_priority(0);
+        assert_eq!(mrx.tier_weight(&0), None);
+        let mut rest: Vec<_> = (0..6).map(|_| mrx.receive()).collect();
+        rest.sort_unstable();
+        assert_eq!(rest, (100..106).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn try_iter() {
+        let mrx = DynMultiReceiver::<u32, u16>::new();
+        let high = mrx.new_channel(0, 1, false, None);
+        let frozen = mrx.new_channel(1, 1, true, None);
+        let low = mrx.new_channel(2, 1, false, None);
+        for x in 0..3 {
+            low.send(20 + x).unwrap();
+            frozen.send(10 + x).unwrap();
+            high.send(x).unwrap();
+        }
+        let drained: Vec<_> = mrx.try_iter().collect();
+        assert_eq!(drained, vec![0, 1, 2, 20, 21, 22]);
+        assert_eq!(mrx.try_iter().next(), None);
+        assert_eq!(mrx.len(), 3);
+        frozen.set_frozen(false);
+        assert_eq!(mrx.try_iter().collect::<Vec<_>>(), vec![10, 11, 12]);
+    }
+
+    #[test]
+    fn iter() {
+        let mrx = DynMultiReceiver::<u32, u16>::new();
+        let producers: Vec<_> = (0..3u32)
+            .map(|p| {
+                let sender = mrx.new_channel(p as u16, 1, false, Some(2));
+                std::thread::spawn(move || {
+                    for x in 0..5 {
+                        sender.send(p * 10 + x).unwrap();
+                    }
+                    // The sender is dropped here
+                })
+            })
+            .collect();
+        let consumers: Vec<_> = (0..2)
+            .map(|_| {
+                let mrx = mrx.clone();
+                std::thread::spawn(move || mrx.iter().collect::<Vec<_>>())
+            })
+            .collect();
+        for producer in producers {
+            producer.join().unwrap();
+        }
+        let mut received: Vec<_> = consumers
+            .into_iter()
+            .flat_map(|c| c.join().unwrap())
+            .collect();
+        received.sort_unstable();
+        let expected: Vec<_> = (0..3)
+            .flat_map(|p| (0..5).map(move |x| p * 10 + x))
+            .collect();
+        assert_eq!(received, expected);
+        assert!(mrx.no_channels());
+        assert_eq!(mrx.iter().next(), None);
+    }
+
+    #[test]
+    fn recv_disconnected() {
+        let mrx = DynMultiReceiver::<u32, u16>::new();
+        assert_eq!(mrx.recv(), Err(RecvError::Disconnected));
+        let sender = mrx.new_channel(0, 1, false, None);
+        sender.send(0).unwrap();
+        let waiting = {
+            let mrx = mrx.clone();
+            std::thread::spawn(move || (mrx.recv(), mrx.recv()))
+        };
+        std::thread::sleep(Duration::from_millis(50));
+        mrx.remove_channel(&sender);
+        assert_eq!(
+            waiting.join().unwrap(),
+            (Ok(0), Err(RecvError::Disconnected))
+        );
+    }
+
+    #[test]
+    fn send_all() {
+        let mrx = DynMultiReceiver::<u32, u16>::new();
+        let unbounded = mrx.new_channel(1, 1, false, None);
+        unbounded.send_all(0..1000).unwrap();
+        assert_eq!(mrx.len(), 1000);
+        assert_eq!(
+            (0..1000).map(|_| mrx.receive()).collect::<Vec<_>>(),
+            (0..1000).collect::<Vec<_>>()
+        );
+
+        // A full channel announces the values sent so far before blocking
+        let bounded = mrx.new_channel(1, 1, false, Some(10));
+        let zero = mrx.new_channel(2, 1, false, Some(0));
+        let receiver = mrx.clone();
+        let receiving =
+            std::thread::spawn(move || (0..1010).map(|_| receiver.receive()).collect::<Vec<_>>());
+        bounded.send_all(0..1000).unwrap();
+        zero.send_all(1000..1010).unwrap();
+        let mut received = receiving.join().unwrap();
+        received.sort_unstable();
+        assert_eq!(received, (0..1010).collect::<Vec<_>>());
+
+        let limited = mrx.new_limited_channel(1, 1, false, None, 5);
+        assert!(matches!(limited.send_all(0..10), Err(SendError::Exhausted)));
+        assert_eq!(mrx.len(), 5);
+    }
+
+    #[test]
+    fn clone_sender() {
+        let mrx = DynMultiReceiver::<u32, u16>::new();
+        let sender = mrx.new_channel(1, 1, false, Some(8));
+        let producers: Vec<_> = (0..4)
+            .map(|producer| {
+                let sender = sender.clone();
+                std::thread::spawn(move || {
+                    assert_eq!(sender.id(), 0);
+                    for i in 0..100 {
+                        sender.send(producer * 100 + i).unwrap();
+                    }
+                })
+            })
+            .collect();
+        let mut received: Vec<_> = (0..400).map(|_| mrx.receive()).collect();
+        for producer in producers {
+            producer.join().unwrap();
+        }
+        received.sort_unstable();
+        assert_eq!(received, (0..400).collect::<Vec<_>>());
+        // Dropping the clones left the channel open
+        assert!(!mrx.no_channels());
+        sender.send(400).unwrap();
+        drop(sender);
+        assert_eq!(mrx.recv(), Ok(400));
+        assert_eq!(mrx.recv(), Err(RecvError::Disconnected));
+    }
+
+    #[test]
+    fn remove_channel_draining() {
+        let mrx = DynMultiReceiver::<u32, u16>::new();
+        let removed = mrx.new_channel(1, 1, false, None);
+        let other = mrx.new_channel(1, 1, false, None);
+        removed.send_all(0..5).unwrap();
+        other.send(5).unwrap();
+        assert_eq!(mrx.remove_channel_draining(&removed), vec![0, 1, 2, 3, 4]);
+        assert!(removed.send(6).is_err());
+        assert_eq!(mrx.remove_channel_draining(&removed), vec![]);
+        assert_eq!(mrx.len(), 1);
+        assert_eq!(mrx.receive(), 5);
+        assert!(mrx.is_empty());
+    }
+
+    #[test]
+    fn close() {
+        let mrx = DynMultiReceiver::<u32, u16>::new();
+        let high = mrx.new_channel(1, 1, false, None);
+        let low = mrx.new_channel(2, 1, false, None);
+        let clone = low.clone();
+        low.send_all(0..3).unwrap();
+        high.send(10).unwrap();
+        low.close();
+        assert!(matches!(clone.send(3), Err(SendError::Disconnected)));
+        assert_eq!(clone.try_send(3), Err(TrySendError::Disconnected(3)));
+        // The buffered messages are still delivered in priority order
+        assert_eq!(
+            (0..4).map(|_| mrx.receive()).collect::<Vec<_>>(),
+            vec![10, 0, 1, 2]
+        );
+        assert_eq!(mrx.channels_at_priority(&2), vec![]);
+        assert!(!mrx.no_channels());
+
+        // An empty channel is removed right away
+        high.close();
+        assert!(mrx.no_channels());
+        assert_eq!(mrx.recv(), Err(RecvError::Disconnected));
+    }
+
+    #[test]
+    fn sender_count() {
+        let mrx = DynMultiReceiver::<u32, u16>::new();
+        let sender = mrx.new_channel(1, 1, false, None);
+        let other = mrx.new_channel(1, 1, false, None);
+        assert_eq!(sender.sender_count(), 1);
+        let clones: Vec<_> = (0..3).map(|_| sender.clone()).collect();
+        assert_eq!(sender.sender_count(), 4);
+        assert_eq!(clones[0].sender_count(), 4);
+        assert_eq!(other.sender_count(), 1);
+        drop(clones);
+        assert_eq!(sender.sender_count(), 1);
+        let clone = sender.clone();
+        drop(sender);
+        assert_eq!(clone.sender_count(), 1);
+    }
+
+    #[test]
+    fn drop_sender_removes_channel() {
+        let mrx = DynMultiReceiver::<u32, u16>::new();
+        let idle = mrx.new_channel(1, 1, false, None);
+        let busy = mrx.new_channel(2, 1, false, None);
+        let clone = idle.clone();
+        busy.send(1).unwrap();
+        drop(idle);
+        assert_eq!(mrx.channels_at_priority(&1).len(), 1);
+        drop(clone);
+        assert!(mrx.channels_at_priority(&1).is_empty());
+        // A channel with buffered messages stays until they are delivered
+        drop(busy);
+        assert!(!mrx.no_channels());
+        assert_eq!(mrx.receive(), 1);
+        assert!(mrx.no_channels());
+    }
+
+    #[test]
+    fn reap_disconnected() {
+        let mrx = DynMultiReceiver::<u32, u16>::new();
+        let a = mrx.new_channel(1, 1, false, None);
+        let b = mrx.new_channel(2, 1, false, None);
+        // Holding the lock keeps dropped senders from removing their channels right away
+        let scanning = mrx.state.read().unwrap();
+        drop(a);
+        drop(b);
+        drop(scanning);
+        assert!(!mrx.no_channels());
+        let mut reaped = mrx.reap_disconnected();
+        reaped.sort_unstable();
+        assert_eq!(reaped, vec![0, 1]);
+        assert!(mrx.no_channels());
+
+        // Buffered messages are still delivered
+        let c = mrx.new_channel(1, 1, false, None);
+        c.send(1).unwrap();
+        drop(c);
+        assert!(mrx.reap_disconnected().is_empty());
+        assert_eq!(mrx.receive(), 1);
+        assert!(mrx.no_channels());
+    }
+
+    #[test]
+    fn wait_for_channel() {
+        let mrx = DynMultiReceiver::<u32, u16>::new();
+        let (tx, rx) = crossbeam_channel::unbounded();
+        let receiver = mrx.clone();
+        let waiting = std::thread::spawn(move || {
+            receiver.wait_for_channel();
+            tx.send(()).unwrap();
+        });
+        assert!(rx.recv_timeout(Duration::from_millis(100)).is_err());
+        let sender = mrx.new_channel(1, 1, false, None);
+        assert_eq!(rx.recv_timeout(Duration::from_secs(5)), Ok(()));
+        waiting.join().unwrap();
+        // Returns right away while there is a channel
+        mrx.wait_for_channel();
+        drop(sender);
+    }
+
+    #[test]
+    fn receiver_count() {
+        let mrx = DynMultiReceiver::<u32, u16>::new();
+        assert_eq!(mrx.receiver_count(), 1);
+        let clones: Vec<_> = (0..3).map(|_| mrx.clone()).collect();
+        assert_eq!(mrx.receiver_count(), 4);
+        assert_eq!(clones[2].receiver_count(), 4);
+        drop(clones);
+        assert_eq!(mrx.receiver_count(), 1);
+        let clone = mrx.clone();
+        let sender = mrx.new_channel(1, 1, false, None);
+        drop(mrx);
+        assert_eq!(clone.receiver_count(), 1);
+        drop(clone);
+        assert!(matches!(sender.send(0), Err(SendError::Disconnected)));
+    }
+
+    #[test]
+    fn remove_last_channel_wakes_receivers() {
+        let mrx = DynMultiReceiver::<u32, u16>::new();
+        let sender = mrx.new_channel(0, 1, false, None);
+        let waiting_timeout = {
+            let mrx = mrx.clone();
+            std::thread::spawn(move || mrx.receive_timeout(Duration::from_secs(60)))
+        };
+        let waiting_iter = {
+            let mrx = mrx.clone();
+            std::thread::spawn(move || mrx.iter().next())
+        };
+        std::thread::sleep(Duration::from_millis(50));
+        assert!(!waiting_timeout.is_finished());
+        assert!(!waiting_iter.is_finished());
+        let start = Instant::now();
+        mrx.remove_channel(&sender);
+        assert_eq!(
+            waiting_timeout.join().unwrap(),
+            Err(RecvTimeoutError::Disconnected)
+        );
+        assert_eq!(waiting_iter.join().unwrap(), None);
+        assert!(start.elapsed() < Duration::from_secs(10));
+    }
+
+    #[test]
+    fn one_sender_many_receivers() {
+        let barrier = Arc::new(Barrier::new(100));
+        let mrx = DynMultiReceiver::<i32, u16>::new();
+        let sender = mrx.new_channel(10, 10, false, None);
+        let mut receivers = Vec::new();
+        for _ in 0..100 {
+            receivers.push(mrx.clone());
+        }
+
+        for x in 0..100 {
+            sender.send(x).unwrap();
+        }
+
+        let mut receiver_threads = Vec::new();
+        for receiver in receivers {
+            let barrier = barrier.clone();
+            receiver_threads.push(std::thread::spawn(move || {
+                barrier.wait();
+                receiver.receive()
+            }));
+        }
+
+        let mut received = Vec::new();
+        for thread in receiver_threads {
+            received.push(thread.join().unwrap());
+        }
+        received.sort_unstable();
+        for (idx, msg) in received.iter().enumerate() {
+            assert_eq!(idx, *msg as usize);
+        }
+    }
+
+    #[test]
+    #[cfg(not(feature = "parking-lot"))]
+    fn recv_poisoned() {
+        let mrx = Arc::new(DynMultiReceiver::<i32, u16>::new());
+        let sender = mrx.new_channel(10, 10, false, None);
+        sender.send(0).unwrap();
+        assert_eq!(mrx.recv(), Ok(0));
+
+        // Poison the state lock by panicking while holding it
+        let poisoner = mrx.clone();
+        let _ = std::thread::spawn(move || {
+            let _state = poisoner.state.write().unwrap();
+            panic!("poison");
+        })
+        .join();
+        sender.send(1).unwrap();
+        assert_eq!(mrx.recv(), Err(RecvError::Poisoned));
+    }
+
+    #[test]
+    fn recv_inconsistent_links() {
+        let mrx = DynMultiReceiver::<i32, u16>::new();
+        let sender = mrx.new_channel(10, 10, false, None);
+        // A link to a channel the lookup doesn't know
+        mrx.state
+            .write()
+            .unwrap()
+            .links
+            .entry(99)
+            .or_default()
+            .push(sender.id());
+        sender.send(0).unwrap();
+        assert!(matches!(mrx.recv(), Err(RecvError::Internal(_))));
+    }
+
+    #[test]
+    fn recv_cleanup_of_removed_channel() {
+        let mrx = DynMultiReceiver::<i32, u16>::new();
+        let sender = mrx.new_channel(1, 10, false, None);
+        let other = mrx.new_channel(10, 10, false, None);
+        // The channel is removed explicitly after it was scheduled for cleanup
+        mrx.schedule_cleanup(sender.id()).unwrap();
+        mrx.remove_channel(&sender);
+        other.send(0).unwrap();
+        assert_eq!(mrx.recv(), Ok(0));
+    }
+
+    #[test]
+    fn disconnect() {
+        let mrx = DynMultiReceiver::<i32, u16>::new();
+        let sender = mrx.new_channel(10, 10, false, None);
+        mrx.remove_channel(&sender);
+        assert!(mrx.no_channels());
+        assert!(sender.send(0).is_err());
+    }
+
+    #[test]
+    fn is_frozen() {
+        let mrx = DynMultiReceiver::<i32, u16>::new();
+        let sender = mrx.new_channel(10, 10, true, None);
+        assert!(sender.is_frozen());
+        sender.set_frozen(false);
+        assert!(!sender.is_frozen());
+        mrx.set_frozen_many([sender.id()], true);
+        assert!(sender.is_frozen());
+        mrx.remove_channel(&sender);
+        assert!(!sender.is_frozen());
+    }
+
+    #[test]
+    fn set_frozen_after_remove() {
+        let mrx = DynMultiReceiver::<i32, u16>::new();
+        let sender = mrx.new_channel(10, 10, false, None);
+        sender.send(0).unwrap();
+        mrx.remove_channel(&sender);
+        let wakes = mrx.pending_wakes();
+        sender.set_frozen(true);
+        sender.set_frozen(false);
+        assert_eq!(mrx.pending_wakes(), wakes);
+        assert!(mrx.no_channels());
+    }
+
+    #[test]
+    fn double_remove() {
+        let mrx = DynMultiReceiver::<i32, u16>::new();
+        let sender = mrx.new_channel(10, 10, false, None);
+        let other = mrx.new_channel(10, 10, false, None);
+        assert!(mrx.remove_channel_by_id(sender.id()));
+        assert!(!mrx.remove_channel_by_id(sender.id()));
+        assert!(!mrx.remove_channel(&sender));
+        assert!(!mrx.remove_channel_by_id(1234));
+        // Two consumers racing to remove the same channel
+        let removers: Vec<_> = (0..2)
+            .map(|_| {
+                let mrx = mrx.clone();
+                let id = other.id();
+                std::thread::spawn(move || mrx.remove_channel_by_id(id))
+            })
+            .collect();
+        let removed: Vec<bool> = removers.into_iter().map(|r| r.join().unwrap()).collect();
+        assert_eq!(removed.iter().filter(|r| **r).count(), 1);
+        assert!(mrx.no_channels());
     }
 
     #[test]
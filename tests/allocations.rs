@@ -0,0 +1,62 @@
+//! Checks that receiving doesn't allocate once the multichannel reached a steady state.
+//! Lives in its own test binary, as it replaces the global allocator.
+
+use std::{
+    alloc::{GlobalAlloc, Layout, System},
+    cell::Cell,
+};
+
+use multichannel::{DynMultiReceiver, ReceiveScratch};
+
+/// Counts the allocations of the current thread.
+struct CountingAllocator;
+
+thread_local! {
+    static ALLOCATIONS: Cell<usize> = const { Cell::new(0) };
+}
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let _ = ALLOCATIONS.try_with(|count| count.set(count.get() + 1));
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+fn allocations() -> usize {
+    ALLOCATIONS.with(|count| count.get())
+}
+
+#[test]
+fn receive_doesnt_allocate() {
+    let mrx = DynMultiReceiver::<u32, u16>::new();
+    let senders = (1..=1000)
+        .map(|weight| mrx.new_channel(1, weight, false, None))
+        .collect::<Vec<_>>();
+    for sender in &senders {
+        for _ in 0..10 {
+            sender.send(sender.id()).unwrap();
+        }
+    }
+    // The first receive builds the weight table and grows the buffers of this consumer
+    mrx.receive();
+    let before = allocations();
+    for _ in 0..1000 {
+        mrx.receive();
+    }
+    assert_eq!(allocations() - before, 0);
+
+    let mut scratch = ReceiveScratch::new();
+    mrx.receive_with_scratch(&mut scratch);
+    let before = allocations();
+    for _ in 0..1000 {
+        mrx.receive_with_scratch(&mut scratch);
+    }
+    assert_eq!(allocations() - before, 0);
+}